@@ -117,8 +117,11 @@ narration_interval = 10
 last_classification_time = 0
 classification_interval = 0.1  # Classify every 100ms for real-time updates
 
-# Tauri communication
-TAURI_URL = "http://localhost:3030/api/message"
+# Tauri communication (the app passes its port when it launches us)
+TAURI_BASE_URL = f"http://localhost:{os.environ.get('DUCK_SERVER_PORT', '3030')}"
+TAURI_URL = f"{TAURI_BASE_URL}/api/message"
+# Bearer token the app requires on /api/message when DUCK_API_TOKEN is set
+TAURI_HEADERS = {'Authorization': f"Bearer {os.environ['DUCK_API_TOKEN'].strip()}"} if os.environ.get('DUCK_API_TOKEN', '').strip() else {}
 last_tauri_send_time = 0
 tauri_send_interval = 0.5  # Send to Tauri every 500ms
 
@@ -176,7 +179,7 @@ def send_focus_restoration_video():
         filename = Path(video_path).name
         video_url = f'http://localhost:{flask_port}/video/{filename}'
 
-        response = requests.post('http://localhost:3030/api/video', json={
+        response = requests.post(f'{TAURI_BASE_URL}/api/video', json={
             'video_url': video_url,
             'timestamp': datetime.now().isoformat()
         }, timeout=2)
@@ -234,7 +237,7 @@ def check_and_send_duck_alert():
                 }
             }
 
-            response = requests.post(TAURI_URL, json=payload, timeout=1)
+            response = requests.post(TAURI_URL, json=payload, headers=TAURI_HEADERS, timeout=1)
             if response.status_code == 200:
                 last_duck_sent_time = current_time
                 duck_alert_was_sent = True  # Set flag to trigger video on focus restoration
@@ -275,7 +278,7 @@ def send_to_tauri():
             "metrics": current_metrics
         }
 
-        response = requests.post(TAURI_URL, json=payload, timeout=1)
+        response = requests.post(TAURI_URL, json=payload, headers=TAURI_HEADERS, timeout=1)
         if response.status_code == 200:
             last_tauri_send_time = current_time
 
@@ -435,6 +438,7 @@ def update_all_metrics():
     current_metrics['focus_score'] = float(focus_score)
     current_metrics['distraction_score'] = float(distraction_score)
     current_metrics['attention_confidence'] = float(confidence)
+    current_metrics['updated_at'] = time.time()
 
 def classify_emotion_state():
     """Legacy - now called by update_all_metrics"""
@@ -1003,7 +1007,8 @@ def api_metrics():
         'head_orientation': current_metrics['head_orientation'],
         'heart_rate': current_metrics['heart_rate'],
         'movement_intensity': current_metrics['movement_intensity'],
-        'theta_beta_ratio': current_metrics.get('attention_confidence', 0)  # Using confidence as theta_beta proxy
+        'theta_beta_ratio': current_metrics.get('attention_confidence', 0),  # Using confidence as theta_beta proxy
+        'timestamp': current_metrics.get('updated_at')  # Unix seconds of the last classification
     })
 
 @app.route('/video/<filename>')
@@ -1041,7 +1046,7 @@ def generate_video_endpoint():
 
         # Send video path to Tauri
         video_url = f'file://{output_path.absolute()}'
-        requests.post('http://localhost:3030/api/video', json={
+        requests.post(f'{TAURI_BASE_URL}/api/video', json={
             'video_url': video_url,
             'timestamp': datetime.now().isoformat()
         })
@@ -0,0 +1,149 @@
+// Statistics over the in-memory metrics and transition history
+use chrono::{DateTime, Utc};
+
+// Pearson correlation coefficient of two equally sized series.
+// Returns None with fewer than two points or when either series has no variance.
+pub fn pearson_correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return None;
+    }
+
+    let mean_x = xs[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = ys[..n].iter().sum::<f64>() / n as f64;
+
+    let mut covariance = 0.0;
+    let mut var_x = 0.0;
+    let mut var_y = 0.0;
+    for (x, y) in xs[..n].iter().zip(&ys[..n]) {
+        let dx = x - mean_x;
+        let dy = y - mean_y;
+        covariance += dx * dy;
+        var_x += dx * dx;
+        var_y += dy * dy;
+    }
+
+    if var_x <= f64::EPSILON || var_y <= f64::EPSILON {
+        return None;
+    }
+
+    let r = covariance / (var_x.sqrt() * var_y.sqrt());
+    r.is_finite().then_some(r.clamp(-1.0, 1.0))
+}
+
+// Least-squares slope of y over x. Returns None with fewer than two points or no spread in x.
+pub fn linear_slope(points: &[(f64, f64)]) -> Option<f64> {
+    let n = points.len();
+    if n < 2 {
+        return None;
+    }
+
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n as f64;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n as f64;
+    let mut covariance = 0.0;
+    let mut var_x = 0.0;
+    for (x, y) in points {
+        covariance += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x) * (x - mean_x);
+    }
+
+    if var_x <= f64::EPSILON {
+        return None;
+    }
+    let slope = covariance / var_x;
+    slope.is_finite().then_some(slope)
+}
+
+// A run of consecutive samples in the same state
+#[derive(Debug, Clone)]
+pub struct StateSegment {
+    pub state: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub avg_score: f64,
+}
+
+// Collapse samples into contiguous state segments. A segment ends where the next
+// one starts, or at its last sample when followed by a gap longer than `max_gap_secs`.
+pub fn state_segments<'a, I>(samples: I, max_gap_secs: f64) -> Vec<StateSegment>
+where
+    I: IntoIterator<Item = (DateTime<Utc>, &'a str, f64)>,
+{
+    let mut segments: Vec<StateSegment> = Vec::new();
+    let mut score_sum = 0.0;
+    let mut count = 0usize;
+    let mut last_at: Option<DateTime<Utc>> = None;
+
+    for (at, state, score) in samples {
+        let gap = last_at.map(|prev| (at - prev).num_milliseconds() as f64 / 1000.0);
+        let continues = gap.is_some_and(|g| g <= max_gap_secs);
+
+        match segments.last_mut() {
+            Some(current) if continues && current.state == state => {
+                current.end = at;
+                score_sum += score;
+                count += 1;
+                current.avg_score = score_sum / count as f64;
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    if continues {
+                        previous.end = at;
+                    }
+                }
+                segments.push(StateSegment {
+                    state: state.to_string(),
+                    start: at,
+                    end: at,
+                    avg_score: score,
+                });
+                score_sum = score;
+                count = 1;
+            }
+        }
+        last_at = Some(at);
+    }
+
+    segments
+}
+
+// Seconds from each switch to "unfocused" until the next switch back to "focused"
+pub fn refocus_durations<'a, I>(transitions: I) -> Vec<f64>
+where
+    I: IntoIterator<Item = (DateTime<Utc>, &'a str)>,
+{
+    let mut durations = Vec::new();
+    let mut distracted_since: Option<DateTime<Utc>> = None;
+
+    for (at, state) in transitions {
+        match state {
+            "unfocused" => {
+                distracted_since.get_or_insert(at);
+            }
+            "focused" => {
+                if let Some(start) = distracted_since.take() {
+                    durations.push((at - start).num_milliseconds() as f64 / 1000.0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    durations
+}
+
+// Middle value of the series, averaging the two middle values for even lengths
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        Some(sorted[mid])
+    } else {
+        Some((sorted[mid - 1] + sorted[mid]) / 2.0)
+    }
+}
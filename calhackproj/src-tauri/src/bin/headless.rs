@@ -0,0 +1,4 @@
+// Runs the monitor and HTTP/WebSocket server without opening a Tauri window
+fn main() {
+    calhackproj_lib::run_headless()
+}
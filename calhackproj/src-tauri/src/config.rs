@@ -0,0 +1,68 @@
+// Small persisted settings stored in duck_config.json
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use tracing::warn;
+
+use crate::{ClassificationMode, DEFAULT_DISCONNECT_THRESHOLD, DEFAULT_STABILITY_WINDOW, POLL_INTERVAL_MS};
+
+// Tunables applied at startup and by reload_config; missing fields keep the
+// built-in defaults and unrelated keys (e.g. last_muse_port) are ignored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub poll_interval_ms: u64,
+    pub stability_window_secs: f64,
+    pub disconnect_threshold: u32,
+    // Bridge ports to probe; MUSE_API_PORTS takes precedence. Read at startup only.
+    pub muse_ports: Option<Vec<u16>>,
+    pub classification_mode: ClassificationMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            poll_interval_ms: POLL_INTERVAL_MS,
+            stability_window_secs: DEFAULT_STABILITY_WINDOW.as_secs_f64(),
+            disconnect_threshold: DEFAULT_DISCONNECT_THRESHOLD,
+            muse_ports: None,
+            classification_mode: ClassificationMode::default(),
+        }
+    }
+}
+
+// Load the tunables; a missing file gives the defaults, an unreadable or invalid one is an error
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+}
+
+// Read the config file as a JSON object; missing or invalid files read as empty
+pub fn read_config(path: &Path) -> Map<String, Value> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Map::new();
+    };
+    match serde_json::from_str::<Value>(&contents) {
+        Ok(Value::Object(map)) => map,
+        Ok(_) | Err(_) => {
+            warn!("⚠️ Ignoring malformed config file {}", path.display());
+            Map::new()
+        }
+    }
+}
+
+// Set a single key, preserving everything else in the file
+pub fn update_config_key(path: &Path, key: &str, value: Value) -> std::io::Result<()> {
+    let mut config = read_config(path);
+    config.insert(key.to_string(), value);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(&Value::Object(config)).map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
@@ -1,19 +1,213 @@
-use std::sync::{Arc, Mutex};
-use std::process::{Command, Child};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::process::{Command, Child, Stdio};
 use tauri::{Manager, Emitter};
 use serde::{Deserialize, Serialize};
 use axum::{
-    extract::{State, WebSocketUpgrade, ws::{WebSocket, Message}},
+    extract::{Path, Query, State, WebSocketUpgrade, ws::{WebSocket, Message}},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
-use futures_util::{SinkExt, StreamExt};
-use tokio::sync::broadcast;
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use tokio::sync::{broadcast, mpsc, watch};
 use tower_http::cors::{CorsLayer, Any};
+use tracing::{debug, error, info, warn};
 
-// Global port configuration
-const MUSE_API_PORTS: &[u16] = &[5000, 5001, 5002, 5003, 5004, 5005];
+mod analytics;
+mod config;
+#[cfg(feature = "mock")]
+mod mock_muse;
+mod muse_schema;
+mod storage;
+mod syslog;
+
+#[cfg(feature = "mock")]
+pub use mock_muse::{start_mock_muse, MockMuse, MockStep};
+pub use muse_schema::MuseSchema;
+
+// Global port configuration; MUSE_API_PORTS / MUSE_API_PORT override at startup
+const DEFAULT_MUSE_API_PORTS: &[u16] = &[5000, 5001, 5002, 5003, 5004, 5005];
+const MUSE_API_PORTS_ENV: &str = "MUSE_API_PORTS";
+const MUSE_API_PORT_ENV: &str = "MUSE_API_PORT";
+
+// Shared secret required as `Authorization: Bearer <token>` on POST /api/message
+const API_TOKEN_ENV: &str = "DUCK_API_TOKEN";
+
+// Local HTTP + WebSocket server address; SERVER_HOST picks the interface and
+// the first free port in the range is used
+const SERVER_HOST_ENV: &str = "SERVER_HOST";
+const DEFAULT_SERVER_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const HTTP_SERVER_PORTS: std::ops::RangeInclusive<u16> = 3030..=3040;
+
+// Monitor cadence; the interval can be changed at runtime within these bounds
+const POLL_INTERVAL_MS: u64 = 500;
+const MIN_POLL_INTERVAL_MS: u64 = 100;
+const MAX_POLL_INTERVAL_MS: u64 = 10_000;
+const LOOP_TIMING_WINDOW: usize = 20;
+const LOOP_OVERRUN_WARN_STREAK: u32 = 5;
+
+// Upper bound for per-client WebSocket coalescing
+const MAX_WS_BUFFER_MS: u64 = 10_000;
+
+// Keepalive: ping every interval; a client silent for interval + timeout is dropped
+const WS_PING_INTERVAL_SECS: u64 = 15;
+const WS_PONG_TIMEOUT_SECS: u64 = 10;
+
+// Liveness `heartbeat` broadcast while nothing else is happening; 0 disables
+const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 30;
+const MAX_HEARTBEAT_INTERVAL_SECS: u64 = 3600;
+
+// Messages targeted at one WebSocket client that may queue before it is considered stuck
+const WS_DIRECT_QUEUE_SIZE: usize = 32;
+
+// Messages buffered per WebSocket subscriber before it lags and is resynced.
+// Each slot holds a cloned DuckMessage (a few hundred bytes), so memory grows
+// with capacity, not with the number of clients
+const WS_CHANNEL_CAPACITY_ENV: &str = "DUCK_WS_CHANNEL_CAPACITY";
+const DEFAULT_WS_CHANNEL_CAPACITY: usize = 100;
+const MIN_WS_CHANNEL_CAPACITY: usize = 16;
+
+// Trailing window for the messages-per-minute rate in ServiceStatus
+const MESSAGE_RATE_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+// Highest WebSocket envelope version a client can request with ?v=
+const WS_ENVELOPE_VERSION: u32 = 1;
+
+// Buffer sizes for in-memory history
+const ANNOTATION_BUFFER_SIZE: usize = 500;
+const TRANSITION_HISTORY_SIZE: usize = 1000;
+const METRICS_HISTORY_SIZE: usize = 600; // ~5 minutes at 500ms polling
+const LOG_BUFFER_SIZE: usize = 1000;
+// Longer backend output lines are split so a runaway line can't grow without bound
+const MAX_LOG_LINE_BYTES: usize = 8 * 1024;
+
+// File names inside the app's config/data directories
+const CONFIG_FILE_NAME: &str = "duck_config.json";
+const DB_FILE_NAME: &str = "duck_history.db";
+const HISTORY_FILE_NAME: &str = "focus_history.jsonl";
+
+// Human-readable transition log is rotated to `<path>.1` past this size
+const TRANSITION_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+// Longest gap a single persisted sample may account for
+const MAX_SAMPLE_GAP_SECS: f64 = 5.0;
+
+// Recent samples inspected by diagnose_headset (~10s at 500ms polling)
+const HEADSET_DIAGNOSIS_WINDOW: usize = 20;
+
+// Default weight of the newest reading in the smoothed focus score; 1 disables smoothing
+const DEFAULT_SCORE_SMOOTHING_ALPHA: f64 = 0.3;
+
+// Recent span and sample count used for the focus velocity regression
+const FOCUS_VELOCITY_WINDOW_SECS: f64 = 10.0;
+const MIN_VELOCITY_SAMPLES: usize = 5;
+
+// Minimum samples before reporting a heart rate / focus correlation
+const MIN_CORRELATION_SAMPLES: usize = 10;
+
+// Heart rates outside this range (bpm) are treated as sensor noise
+const PLAUSIBLE_HEART_RATE: std::ops::RangeInclusive<f64> = 25.0..=250.0;
+
+// Consecutive failed polls before the headset is reported disconnected
+const DEFAULT_DISCONNECT_THRESHOLD: u32 = 5;
+
+// How long a new state must hold before a nudge, and how far it may be set or tuned
+const DEFAULT_STABILITY_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+const MAX_STABILITY_WINDOW_SECS: f64 = 10.0;
+
+// History used for time-of-day advice, and the evidence needed for full confidence
+const OPTIMAL_TIMES_LOOKBACK_DAYS: u32 = 28;
+const OPTIMAL_TIMES_FULL_CONFIDENCE_DAYS: f64 = 5.0;
+const OPTIMAL_TIMES_FULL_CONFIDENCE_SAMPLES: f64 = 600.0;
+
+// Minimum time a user-facing connection state is shown before it may change
+const DEFAULT_CONNECTION_DISPLAY_MIN_SECS: f64 = 3.0;
+const MAX_CONNECTION_DISPLAY_MIN_SECS: f64 = 60.0;
+
+// Minimum gap between connection_status broadcasts; flaps inside it collapse into one
+const DEFAULT_CONNECTION_STATUS_DEBOUNCE_SECS: f64 = 3.0;
+const MAX_CONNECTION_STATUS_DEBOUNCE_SECS: f64 = 60.0;
+
+// Set to run headless mode against the built-in mock bridge (`mock` feature)
+#[cfg(feature = "mock")]
+const MOCK_MUSE_ENV: &str = "DUCK_MOCK_MUSE";
+
+// Where headless mode keeps its config and history
+const HEADLESS_DATA_DIR_ENV: &str = "DUCK_DATA_DIR";
+const HEADLESS_DEFAULT_DATA_DIR: &str = "duck-data";
+
+// Failed frontend emits held for retry at most
+const EMIT_RETRY_QUEUE_SIZE: usize = 50;
+const MAX_EMIT_RETRY_ATTEMPTS: u32 = 20;
+
+// Consecutive failed frontend emits before the window is treated as gone
+const FRONTEND_EMIT_FAILURE_THRESHOLD: u32 = 20;
+
+// Tag reported for history recorded without a session tag
+const UNTAGGED_SESSION_LABEL: &str = "untagged";
+
+// Pending alert inbox: how long alerts live and how many are kept
+const DEFAULT_ALERT_TTL_SECS: u64 = 3600;
+const MAX_ALERT_TTL_SECS: u64 = 7 * 24 * 3600;
+const PENDING_ALERTS_SIZE: usize = 100;
+
+// Head turned away this long counts as looking away
+const LOOK_AWAY_SECS: f64 = 3.0;
+
+// Alerts firing this close together are merged into one message
+const DEFAULT_ALERT_MERGE_WINDOW_MS: u64 = 1000;
+const MAX_ALERT_MERGE_WINDOW_MS: u64 = 10000;
+
+// Upper bound on benchmark_pipeline iterations
+const MAX_BENCHMARK_ITERATIONS: u32 = 10_000_000;
+
+// Message priorities clients can use to triage the stream
+const PRIORITY_INFO: u8 = 0;
+const PRIORITY_NORMAL: u8 = 1;
+const PRIORITY_IMPORTANT: u8 = 2;
+
+// Unacknowledged distraction events kept for acknowledge_distraction
+const DISTRACTION_ACK_BUFFER_SIZE: usize = 100;
+
+// Default FocusScore hysteresis band
+const DEFAULT_FOCUS_ENTER: f64 = 0.4;
+const DEFAULT_FOCUS_EXIT: f64 = 0.6;
+
+// Attention labels containing any of these as whole words count as unfocused
+const DEFAULT_UNFOCUSED_KEYWORDS: &[&str] = &["distracted", "drowsy", "unknown"];
+
+// Default trailing window for get_distraction_rate
+const DEFAULT_DISTRACTION_RATE_WINDOW_SECS: f64 = 3600.0;
+
+// Flagged false positives needed before auto-tuning will act
+const MIN_TUNING_FEEDBACK: usize = 5;
+
+// Python supervisor: how often the child is checked, backoff before a relaunch
+// (doubling per recent restart), and the restart budget per window
+const PYTHON_SUPERVISOR_INTERVAL_SECS: u64 = 2;
+const PYTHON_RESTART_BASE_BACKOFF_SECS: u64 = 1;
+const MAX_PYTHON_RESTARTS: usize = 5;
+const PYTHON_RESTART_WINDOW_SECS: u64 = 60;
+
+// Interpreter, backend directory and entry script; unset uses `python main.py`
+// in the located python-backend directory
+const PYTHON_BIN_ENV: &str = "PYTHON_BIN";
+const DEFAULT_PYTHON_BIN: &str = "python";
+const PYTHON_BACKEND_DIR_ENV: &str = "PYTHON_BACKEND_DIR";
+const PYTHON_BACKEND_SCRIPT_ENV: &str = "PYTHON_BACKEND_SCRIPT";
+const DEFAULT_PYTHON_BACKEND_SCRIPT: &str = "main.py";
+
+// Set to 1 to kill leftover backend processes from earlier runs before launching
+const KILL_STALE_BACKENDS_ENV: &str = "KILL_STALE_BACKENDS";
+
+// Set to 1 to leave the Python backend to an external process (e.g. a dev terminal)
+const SKIP_PYTHON_LAUNCH_ENV: &str = "SKIP_PYTHON_LAUNCH";
 
 // Data structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +225,36 @@ pub struct DuckMessage {
     pub msg_type: String,
     pub focus_state: Option<String>,
     pub metrics: Option<MessageMetrics>,
+    // 0 = info, 1 = normal, 2 = important; filled in from the priority map on broadcast
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    // Set on focus_state_change so clients can acknowledge distractions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    // Strictly increasing across everything this process sends, for ordering and
+    // dedupe when wall-clock timestamps jump; assigned on the way out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    // Every alert condition folded into this message when several fired together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<String>>,
+}
+
+impl DuckMessage {
+    // A `msg_type` message stamped now, with every optional field unset
+    pub fn new(msg_type: &str, message: impl Into<String>) -> DuckMessage {
+        DuckMessage {
+            message: message.into(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            msg_type: msg_type.to_string(),
+            focus_state: None,
+            metrics: None,
+            priority: None,
+            event_id: None,
+            seq: None,
+            conditions: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,491 +266,5420 @@ pub struct MuseMetrics {
     pub heart_rate: f64,
     pub movement_intensity: f64,
     pub theta_beta_ratio: f64,
+    // Unix seconds when the bridge computed these metrics, if it reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_at: Option<f64>,
+    // Moving average of focus_score, filled in on the latest-metrics snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothed_focus_score: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VideoMessage {
-    pub video_url: String,
+impl MuseMetrics {
+    // Bring a bridge reading into range before it is used: focus_score and
+    // movement_intensity are clamped to [0, 1], NaN/inf take the previous reading's
+    // value (0 without one), and heart rates outside the plausible range become 0
+    // (no reading). Returns a note for each adjusted field.
+    pub fn validate_and_clamp(&mut self, previous: Option<&MuseMetrics>) -> Vec<String> {
+        let mut notes = Vec::new();
+        let mut check = |name: &str, value: &mut f64, last: Option<f64>, range: std::ops::RangeInclusive<f64>| {
+            if !value.is_finite() {
+                let replacement = last.filter(|v| v.is_finite()).unwrap_or(0.0);
+                notes.push(format!("{} was {}, using {}", name, value, replacement));
+                *value = replacement;
+            } else if !range.contains(&*value) {
+                let clamped = value.clamp(*range.start(), *range.end());
+                notes.push(format!("{} {} clamped to {}", name, value, clamped));
+                *value = clamped;
+            }
+        };
+        check("focus_score", &mut self.focus_score, previous.map(|p| p.focus_score), 0.0..=1.0);
+        check("movement_intensity", &mut self.movement_intensity, previous.map(|p| p.movement_intensity), 0.0..=1.0);
+        check("theta_beta_ratio", &mut self.theta_beta_ratio, previous.map(|p| p.theta_beta_ratio), 0.0..=f64::MAX);
+        check("heart_rate", &mut self.heart_rate, previous.map(|p| p.heart_rate), 0.0..=f64::MAX);
+
+        let implausible = self.heart_rate != 0.0 && !PLAUSIBLE_HEART_RATE.contains(&self.heart_rate);
+        if implausible {
+            notes.push(format!("heart_rate {:.0} bpm is implausible, ignoring", self.heart_rate));
+            self.heart_rate = 0.0;
+        }
+        notes
+    }
+}
+
+// A polled metrics reading with the time it was received
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSample {
     pub timestamp: String,
+    #[serde(skip)]
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub metrics: MuseMetrics,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct ServiceStatus {
-    pub http_server: bool,
-    pub websocket_server: bool,
-    pub extension_connected: bool,
-    pub messages_received: u32,
-    pub muse_connected: bool,
+pub struct HrFocusCorrelation {
+    pub correlation: Option<f64>,
+    pub sample_size: usize,
 }
 
-// Shared application state
-#[derive(Clone)]
-pub struct AppState {
-    pub ws_tx: broadcast::Sender<DuckMessage>,
-    pub message_count: Arc<Mutex<u32>>,
-    pub tauri_handle: Arc<Mutex<Option<tauri::AppHandle>>>,
-    pub python_process: Arc<Mutex<Option<Child>>>,
-    pub last_focus_state: Arc<Mutex<Option<String>>>,
-    pub last_state_change: Arc<Mutex<Option<std::time::Instant>>>,
-    pub muse_connected: Arc<Mutex<bool>>,
-    pub consecutive_failures: Arc<Mutex<u32>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertDirection {
+    Above,
+    Below,
 }
 
-// Tauri commands
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+// One-shot alert fired the next time the smoothed score crosses `threshold`
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreAlert {
+    pub id: u64,
+    pub threshold: f64,
+    pub direction: AlertDirection,
 }
 
-#[tauri::command]
-async fn get_service_status(state: tauri::State<'_, AppState>) -> Result<ServiceStatus, String> {
-    let message_count = *state.message_count.lock().unwrap();
-    let muse_connected = *state.muse_connected.lock().unwrap();
-    Ok(ServiceStatus {
-        http_server: true,
-        websocket_server: true,
-        extension_connected: state.ws_tx.receiver_count() > 0,
-        messages_received: message_count,
-        muse_connected,
-    })
+// Alert conditions held briefly so ones firing together go out as one message
+#[derive(Debug, Clone)]
+pub struct PendingNudge {
+    pub conditions: Vec<String>,
+    pub message: DuckMessage,
+    pub deadline: std::time::Instant,
 }
 
-// HTTP endpoint to receive messages from Python backend
-async fn receive_message(
-    State(state): State<AppState>,
-    Json(message): Json<DuckMessage>,
-) -> impl IntoResponse {
-    println!("📨 Received from Python: {}", message.message);
+// Heart rate bounds that raise a heart_rate_alert; `hysteresis` bpm must be
+// recovered before the alert can fire again
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HeartRateThresholds {
+    pub high: f64,
+    pub low: f64,
+    pub hysteresis: f64,
+}
 
-    // Increment counter
-    {
-        let mut count = state.message_count.lock().unwrap();
-        *count += 1;
+impl Default for HeartRateThresholds {
+    fn default() -> Self {
+        HeartRateThresholds {
+            high: 100.0,
+            low: 50.0,
+            hysteresis: 5.0,
+        }
     }
+}
 
-    // Emit to Tauri frontend
-    if let Some(app) = state.tauri_handle.lock().unwrap().as_ref() {
-        let _ = app.emit("duck-message", message.clone());
-    }
+// Movement intensity that raises a movement_alert once it holds for `dwell_secs`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MovementAlertConfig {
+    pub threshold: f64,
+    pub dwell_secs: f64,
+}
 
-    // Broadcast to WebSocket clients (browser extension)
-    let _ = state.ws_tx.send(message.clone());
+impl Default for MovementAlertConfig {
+    fn default() -> Self {
+        MovementAlertConfig {
+            threshold: 0.7,
+            dwell_secs: 5.0,
+        }
+    }
+}
 
-    Json(serde_json::json!({
-        "status": "success",
-        "broadcasted": true
-    }))
+// A run of readings above the movement threshold
+#[derive(Debug, Clone, Copy)]
+pub struct FidgetEpisode {
+    pub since: std::time::Instant,
+    pub peak: f64,
+    pub notified: bool,
 }
 
-// HTTP endpoint to receive video from Python backend
-async fn receive_video(
-    State(state): State<AppState>,
-    Json(video): Json<VideoMessage>,
-) -> impl IntoResponse {
-    println!("🎬 Received video: {}", video.video_url);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeartRateZone {
+    #[default]
+    Normal,
+    High,
+    Low,
+}
 
-    // Create message to send to extension
-    let video_message = DuckMessage {
-        message: video.video_url.clone(),
-        timestamp: video.timestamp,
-        msg_type: "video".to_string(),
-        focus_state: None,
-        metrics: None,
-    };
+// Which failed frontend emits are retried, and how many times
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmitRetryConfig {
+    pub max_attempts: u32,
+    pub msg_types: Vec<String>,
+}
 
-    // Emit to Tauri frontend (Activity Log)
-    if let Some(app) = state.tauri_handle.lock().unwrap().as_ref() {
-        let _ = app.emit("duck-message", video_message.clone());
+impl Default for EmitRetryConfig {
+    fn default() -> Self {
+        EmitRetryConfig {
+            max_attempts: 3,
+            msg_types: ["focus_state_change", "composite_alert", "connection_abandoned"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+        }
     }
-
-    // Broadcast to WebSocket clients (browser extension)
-    let _ = state.ws_tx.send(video_message);
-
-    Json(serde_json::json!({
-        "status": "success",
-        "video_url": video.video_url
-    }))
 }
 
-// WebSocket handler for browser extension
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_websocket(socket, state))
+// Failed frontend emits awaiting retry, with attempts left
+#[derive(Debug, Default)]
+pub struct EmitRetryState {
+    pub queue: VecDeque<(DuckMessage, u32)>,
+    pub retried: u64,
+    pub dropped: u64,
 }
 
-async fn handle_websocket(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver) = socket.split();
-    let mut rx = state.ws_tx.subscribe();
+#[derive(Debug, Clone, Serialize)]
+pub struct EmitRetryStats {
+    pub queued: usize,
+    pub retried: u64,
+    pub dropped: u64,
+    // Emits stopped because the frontend kept failing
+    pub frontend_suspended: bool,
+}
 
-    println!("🔌 WebSocket client connected");
+// Run of failed frontend emits; once suspended, emits are skipped until a page loads
+#[derive(Debug, Default)]
+pub struct FrontendEmitHealth {
+    pub consecutive_failures: u32,
+    pub suspended: bool,
+}
 
-    // Send welcome message
-    let welcome = DuckMessage {
-        message: "Connected to Duck Controller!".to_string(),
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        msg_type: "connection".to_string(),
-        focus_state: None,
-        metrics: None,
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct BufferInfo {
+    pub name: String,
+    pub len: usize,
+    pub capacity: usize,
+}
 
-    if sender
-        .send(Message::Text(serde_json::to_string(&welcome).unwrap()))
-        .await
-        .is_err()
-    {
-        return;
-    }
+// Connection state as shown to users, held for a minimum time to avoid flicker
+#[derive(Debug, Clone)]
+pub struct DisplayConnection {
+    pub state: &'static str,
+    pub since: std::time::Instant,
+}
 
-    // Send current EEG connection status
-    let is_connected = *state.muse_connected.lock().unwrap();
-    let status_msg = DuckMessage {
-        message: if is_connected {
-            "EEG Connected".to_string()
-        } else {
-            "EEG Disconnected - Please connect your Muse headset".to_string()
-        },
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        msg_type: "connection_status".to_string(),
-        focus_state: None,
-        metrics: None,
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayConnectionStatus {
+    // "connected", "connecting" or "disconnected"
+    pub state: String,
+    pub shown_for_secs: f64,
+}
 
-    if sender
-        .send(Message::Text(serde_json::to_string(&status_msg).unwrap()))
-        .await
-        .is_err()
-    {
-        return;
-    }
+// Debounces connection_status broadcasts. A change inside the interval is held
+// and only sent once the interval has passed, if it still differs from what
+// clients last saw.
+#[derive(Debug, Default)]
+pub struct ConnectionStatusGate {
+    pub last_sent: Option<(bool, std::time::Instant)>,
+    pub pending: Option<(bool, DuckMessage)>,
+}
 
-    // Spawn task to forward broadcast messages to this WebSocket
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            let json = serde_json::to_string(&msg).unwrap();
-            if sender.send(Message::Text(json)).await.is_err() {
-                break;
+impl ConnectionStatusGate {
+    // Message to send now for a new connection state, if any
+    pub fn offer(
+        &mut self,
+        connected: bool,
+        message: DuckMessage,
+        now: std::time::Instant,
+        min_interval: std::time::Duration,
+    ) -> Option<DuckMessage> {
+        match self.last_sent {
+            Some((_, at)) if now.saturating_duration_since(at) < min_interval => {
+                self.pending = Some((connected, message));
+                None
             }
-        }
-    });
-
-    // Handle incoming messages from WebSocket (if any)
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Text(text) = msg {
-                println!("📩 Received from extension: {}", text);
+            Some((sent, _)) if sent == connected => {
+                self.pending = None;
+                None
+            }
+            _ => {
+                self.pending = None;
+                self.last_sent = Some((connected, now));
+                Some(message)
             }
         }
-    });
+    }
 
-    // Wait for either task to finish (connection closed)
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
+    // Held message to send once the interval has passed; flaps that ended where
+    // they started are dropped
+    pub fn flush(&mut self, now: std::time::Instant, min_interval: std::time::Duration) -> Option<DuckMessage> {
+        let (_, at) = self.last_sent?;
+        if now.saturating_duration_since(at) < min_interval {
+            return None;
+        }
+        let (connected, message) = self.pending.take()?;
+        self.offer(connected, message, now, min_interval)
     }
+}
 
-    println!("🔌 WebSocket client disconnected");
+// An important message kept until acknowledged or expired
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub id: u64,
+    // msg_type of the message that raised it
+    pub kind: String,
+    pub message: String,
+    pub timestamp: String,
+    pub expires_at: String,
+    #[serde(skip)]
+    pub expires: chrono::DateTime<chrono::Utc>,
 }
 
-// Health check endpoint
-async fn health_check() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "status": "running",
-        "service": "Duck Controller - Tauri Backend"
-    }))
+// An in-progress capture of the broadcast stream to a JSON-lines file
+pub struct Recording {
+    pub path: PathBuf,
+    pub include_metrics: bool,
+    pub writer: io::BufWriter<std::fs::File>,
+    pub lines: u64,
 }
 
-// Discover which port the Muse API is running on
-async fn discover_muse_port(client: &reqwest::Client) -> Option<u16> {
-    for &port in MUSE_API_PORTS {
-        let url = format!("http://localhost:{}/api/metrics", port);
-        match client.get(&url).send().await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    println!("✅ Found Muse API on port {}", port);
-                    return Some(port);
-                } else {
-                    println!("⚠️ Port {} responded with status: {}", port, response.status());
-                }
-            }
-            Err(e) => {
-                println!("❌ Port {} error: {}", port, e);
-            }
+impl Recording {
+    // Append one `{recorded_at, kind, data}` line
+    fn write_entry<T: Serialize>(&mut self, kind: &str, data: &T) {
+        let entry = serde_json::json!({
+            "recorded_at": chrono::Utc::now().to_rfc3339(),
+            "kind": kind,
+            "data": data,
+        });
+        match writeln!(self.writer, "{}", entry) {
+            Ok(()) => self.lines += 1,
+            Err(e) => eprintln!("⚠️ Failed to write recording {}: {}", self.path.display(), e),
         }
     }
-    println!("❌ No Muse API found on any port");
-    None
 }
 
-// Background task to monitor Muse metrics and send focus state changes
-async fn monitor_muse_metrics(state: AppState) {
-    let client = reqwest::Client::new();
-    let mut last_connection_message_sent = false;
-    let mut muse_port: Option<u16> = None;
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub lines: u64,
+}
 
-    loop {
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+#[derive(Debug, Clone, Serialize)]
+pub struct HeadsetDiagnosis {
+    pub likely_issue: Option<String>,
+    pub suggestions: Vec<String>,
+}
 
-        // Discover port if not found
-        if muse_port.is_none() {
-            muse_port = discover_muse_port(&client).await;
-            if muse_port.is_none() {
-                handle_muse_failure(&state, &mut last_connection_message_sent, "API not found on any port").await;
-                continue;
-            }
-        }
+#[derive(Debug, Clone, Serialize)]
+pub struct RefocusStats {
+    pub avg_refocus_secs: Option<f64>,
+    pub count: usize,
+}
 
-        // Fetch metrics from Muse backend
-        let url = format!("http://localhost:{}/api/metrics", muse_port.unwrap());
-        match client.get(&url).send().await {
-            Ok(response) => {
-                // Check if response is successful (not 404)
-                if response.status().is_success() {
-                    if let Ok(metrics) = response.json::<MuseMetrics>().await {
-                        // Mark as connected
-                        {
-                            let mut connected = state.muse_connected.lock().unwrap();
-                            let mut failures = state.consecutive_failures.lock().unwrap();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoMessage {
+    pub video_url: String,
+    pub timestamp: String,
+}
 
-                            if !*connected {
-                                println!("✅ Muse EEG connected!");
-                                *connected = true;
-                                *failures = 0;
-                                last_connection_message_sent = false;
+// Annotation posted by an external app (editor plugin, etc.)
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnnotationRequest {
+    pub kind: String,
+    pub context: String,
+    pub timestamp: Option<String>,
+}
 
-                                // Send connection status message
-                                let conn_msg = DuckMessage {
-                                    message: "EEG Connected".to_string(),
-                                    timestamp: chrono::Utc::now().to_rfc3339(),
-                                    msg_type: "connection_status".to_string(),
-                                    focus_state: None,
-                                    metrics: None,
-                                };
-
-                                if let Some(app) = state.tauri_handle.lock().unwrap().as_ref() {
-                                    let _ = app.emit("duck-message", conn_msg.clone());
-                                }
-                                let _ = state.ws_tx.send(conn_msg);
-                            }
-                        }
+// Stored annotation, correlated with the focus state in effect at its timestamp
+#[derive(Debug, Clone, Serialize)]
+pub struct Annotation {
+    pub kind: String,
+    pub context: String,
+    pub timestamp: String,
+    pub focus_state: Option<String>,
+    pub focus_score: Option<f64>,
+}
+
+// Which bridge to use when several Muse API ports respond
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortSelection {
+    #[default]
+    First,
+    LowestPort,
+    HighestPort,
+    // Newest bridge timestamp wins
+    MostRecentData,
+}
 
-                        let current_state = metrics.attention.clone();
+// Where the per-reading focus label comes from: the bridge's `attention` string,
+// our own theta/beta ratio cutoff (below the cutoff counts as focused), or
+// focus_score against the FocusHysteresis thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ClassificationMode {
+    #[default]
+    AttentionLabel,
+    ThetaBeta { cutoff: f64 },
+    FocusScore,
+}
 
-                        println!("🧠 Current attention state: {} (focus_score: {:.2})",
-                                 current_state, metrics.focus_score);
+// Two focus_score thresholds for FocusScore classification: dropping below
+// focus_enter turns unfocused, rising above focus_exit turns focused again,
+// and scores in between keep the previous label so the state doesn't chatter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocusHysteresis {
+    pub focus_enter: f64,
+    pub focus_exit: f64,
+}
 
-                        let mut should_send_message = false;
-                        let mut message_to_send: Option<DuckMessage> = None;
+impl Default for FocusHysteresis {
+    fn default() -> Self {
+        FocusHysteresis {
+            focus_enter: DEFAULT_FOCUS_ENTER,
+            focus_exit: DEFAULT_FOCUS_EXIT,
+        }
+    }
+}
 
-                        {
-                            let mut last_state = state.last_focus_state.lock().unwrap();
-                            let mut last_change = state.last_state_change.lock().unwrap();
+impl FocusHysteresis {
+    fn validate(&self) -> Result<(), String> {
+        let in_range = |v: f64| (0.0..=1.0).contains(&v);
+        if !in_range(self.focus_enter) || !in_range(self.focus_exit) {
+            return Err("focus_enter and focus_exit must be between 0 and 1".to_string());
+        }
+        if self.focus_exit <= self.focus_enter {
+            return Err("focus_exit must be greater than focus_enter".to_string());
+        }
+        Ok(())
+    }
+
+    // Label for `score` given the previously classified state (None starts focused)
+    fn label(&self, previous: Option<&str>, score: f64) -> &'static str {
+        let was_unfocused = previous == Some("unfocused");
+        if was_unfocused {
+            if score > self.focus_exit { "focused" } else { "unfocused" }
+        } else if score < self.focus_enter {
+            "unfocused"
+        } else {
+            "focused"
+        }
+    }
+}
+
+impl ClassificationMode {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            ClassificationMode::ThetaBeta { cutoff } if !cutoff.is_finite() || *cutoff <= 0.0 => {
+                Err("cutoff must be a positive number".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn label(&self, metrics: &MuseMetrics, hysteresis: &FocusHysteresis, previous: Option<&str>) -> String {
+        match self {
+            ClassificationMode::AttentionLabel => metrics.attention.clone(),
+            ClassificationMode::ThetaBeta { cutoff } if metrics.theta_beta_ratio < *cutoff => "focused".to_string(),
+            ClassificationMode::ThetaBeta { .. } => "unfocused".to_string(),
+            ClassificationMode::FocusScore => hysteresis.label(previous, metrics.focus_score).to_string(),
+        }
+    }
+}
+
+// Outcome of probing one Muse API port during discovery
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum PortProbeResult {
+    Connected,
+    HttpStatus(u16),
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortProbe {
+    pub result: PortProbeResult,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortDiagnostics {
+    pub port: u16,
+    // None until discovery has tried this port
+    pub last_attempt: Option<PortProbe>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryReport {
+    pub active_port: Option<u16>,
+    // Set by MUSE_API_PORT, which bypasses discovery
+    pub forced_port: Option<u16>,
+    pub ports: Vec<PortDiagnostics>,
+}
+
+// Minimum seconds between repeat nudges of each kind; 0 lets them fire freely
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct StateCooldowns {
+    #[serde(default)]
+    pub focused: f64,
+    #[serde(default)]
+    pub unfocused: f64,
+    #[serde(default)]
+    pub drowsy: f64,
+}
+
+impl StateCooldowns {
+    fn secs_for(&self, kind: &str) -> f64 {
+        match kind {
+            "focused" => self.focused,
+            "drowsy" => self.drowsy,
+            _ => self.unfocused,
+        }
+    }
+}
+
+// Query parameters accepted on the /ws upgrade
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WsParams {
+    // Coalesce to the latest message per msg_type, flushed every `buffer_ms`
+    pub buffer_ms: Option<u64>,
+    // Payload format version; absent means the original flat DuckMessage JSON
+    pub v: Option<u32>,
+    // Name to address this connection by on POST /api/message/{client_id}; generated if absent
+    pub client_id: Option<String>,
+    // Frame encoding: JSON text (default) or MessagePack binary
+    #[serde(default)]
+    pub format: WsFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WsFormat {
+    #[default]
+    Json,
+    // Same fields as the JSON, as a MessagePack map
+    Msgpack,
+}
+
+// Query parameters for GET /api/history
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HistoryParams {
+    // Most recent samples to return; defaults to (and is capped at) the buffer size
+    pub limit: Option<usize>,
+}
+
+// Versioned wrapper sent to clients that connect with ?v=1:
+//   { "v": 1, "kind": "<msg_type>", "data": { ...DuckMessage... } }
+// `kind` carries the same values as the flat `type` field:
+//   connection, connection_status, connection_display, connection_abandoned,
+//   focus_state_change, score_alert, stale_metrics, look_away, heart_rate_alert,
+//   movement_alert, composite_alert, nudges_suppressed, thresholds_tuned, video,
+//   server_shutting_down, backend_restart, backend_fatal, backend_log, resync,
+//   heartbeat, snapshot, plus
+//   whatever type Python posts to /api/message
+#[derive(Debug, Serialize)]
+struct WsEnvelope<'a> {
+    v: u32,
+    kind: &'a str,
+    data: &'a DuckMessage,
+}
+
+// A committed focus state change, kept for correlating later events
+#[derive(Debug, Clone)]
+pub struct FocusTransition {
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub focus_state: String,
+    pub focus_score: f64,
+    // Score on the last reading before the state started changing
+    pub score_before: f64,
+    pub message: DuckMessage,
+}
+
+// A captured log line from the Python backend
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub source: String,
+    pub line: String,
+}
+
+// N-of-M voting: a classification must win `required` of the last `window` polls
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct VoteConfig {
+    pub window: usize,
+    pub required: usize,
+}
+
+impl Default for VoteConfig {
+    fn default() -> Self {
+        Self { window: 5, required: 3 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VoteStatus {
+    pub window: usize,
+    pub required: usize,
+    pub recent: Vec<String>,
+    pub leading_state: Option<String>,
+    pub leading_votes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WeeklyDigest {
+    pub total_focus_mins: f64,
+    pub avg_daily_focus: f64,
+    pub best_day: Option<String>,
+    pub distraction_count: u32,
+    pub trend_vs_last_week: Option<f64>,
+    pub days_with_data: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayComparison {
+    pub today_avg: Option<f64>,
+    pub yesterday_avg: Option<f64>,
+    pub today_focus_mins: Option<f64>,
+    pub yesterday_focus_mins: Option<f64>,
+    // Change in focus minutes, today minus yesterday
+    pub delta: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FocusTimeRecommendation {
+    pub hour: u8,
+    pub avg_score: f64,
+    // 0-1, grows with the number of days and samples behind the average
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineSegment {
+    pub state: String,
+    pub start: String,
+    pub end: String,
+    pub avg_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineBenchmark {
+    pub iterations: u32,
+    pub avg_ns_per_sample: f64,
+    pub samples_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdTuning {
+    // Flagged false positives the adjustment was based on
+    pub feedback_used: usize,
+    pub debounce_before: f64,
+    pub debounce_after: f64,
+    pub adjusted: bool,
+}
+
+// Running measurements of monitor loop iterations
+#[derive(Debug, Clone, Default)]
+pub struct LoopStats {
+    pub recent_ms: VecDeque<f64>,
+    pub max_ms: f64,
+    pub overruns: u64,
+    pub consecutive_overruns: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoopTiming {
+    pub avg_iteration_ms: f64,
+    pub max_iteration_ms: f64,
+    pub target_ms: u64,
+    pub overruns: u64,
+}
+
+// Accumulated monitoring time and how much of it the headset was connected
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionUptime {
+    pub monitored_secs: f64,
+    pub connected_secs: f64,
+    pub last_tick: Option<std::time::Instant>,
+}
+
+// A user-started focus session, accumulating time per committed focus state
+#[derive(Debug, Clone)]
+pub struct FocusSession {
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub started: std::time::Instant,
+    // State being timed and when it began; None while no state is known (e.g. headset disconnected)
+    pub current_state: Option<String>,
+    pub state_since: std::time::Instant,
+    pub focused_secs: f64,
+    pub unfocused_secs: f64,
+    pub distraction_events: u32,
+    // Focused time in the current unbroken run up to state_since, and the longest run so far
+    pub streak_secs: f64,
+    pub best_streak_secs: f64,
+    // Seconds from each distraction nudge to the user acknowledging it
+    pub ack_latencies_secs: Vec<f64>,
+    // One row per committed state change, for CSV export
+    pub transitions: Vec<SessionTransition>,
+}
+
+// A committed focus state change with the reading that triggered it
+#[derive(Debug, Clone)]
+pub struct SessionTransition {
+    pub timestamp: String,
+    pub focus_state: String,
+    pub focus_score: f64,
+    pub heart_rate: f64,
+    pub theta_beta_ratio: f64,
+    pub movement_intensity: f64,
+}
+
+impl FocusSession {
+    pub fn new(current_state: Option<String>, now: std::time::Instant) -> FocusSession {
+        FocusSession {
+            started_at: chrono::Utc::now(),
+            started: now,
+            current_state,
+            state_since: now,
+            focused_secs: 0.0,
+            unfocused_secs: 0.0,
+            distraction_events: 0,
+            streak_secs: 0.0,
+            best_streak_secs: 0.0,
+            ack_latencies_secs: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    // Credit the time spent in the current state, then switch to `next`
+    pub fn transition(&mut self, next: Option<&str>, now: std::time::Instant) {
+        self.close_span(now);
+        if next == Some("unfocused") && self.current_state.as_deref() != Some("unfocused") {
+            self.distraction_events += 1;
+        }
+        if next != Some("focused") {
+            self.streak_secs = 0.0;
+        }
+        self.current_state = next.map(str::to_string);
+    }
+
+    // Current and best focused runs, including the span still in progress
+    pub fn streak(&self, now: std::time::Instant) -> StreakInfo {
+        let mut current_secs = self.streak_secs;
+        if self.current_state.as_deref() == Some("focused") {
+            current_secs += now.saturating_duration_since(self.state_since).as_secs_f64();
+        }
+        StreakInfo {
+            current_secs,
+            best_secs: self.best_streak_secs.max(current_secs),
+        }
+    }
+
+    fn close_span(&mut self, now: std::time::Instant) {
+        let secs = now.saturating_duration_since(self.state_since).as_secs_f64();
+        match self.current_state.as_deref() {
+            Some("focused") => {
+                self.focused_secs += secs;
+                self.streak_secs += secs;
+                self.best_streak_secs = self.best_streak_secs.max(self.streak_secs);
+            }
+            Some("unfocused") => self.unfocused_secs += secs,
+            _ => {}
+        }
+        self.state_since = now;
+    }
+
+    pub fn finish(&mut self, now: std::time::Instant) -> SessionSummary {
+        self.close_span(now);
+        SessionSummary {
+            started_at: self.started_at.to_rfc3339(),
+            ended_at: chrono::Utc::now().to_rfc3339(),
+            duration_secs: now.saturating_duration_since(self.started).as_secs_f64(),
+            focused_secs: self.focused_secs,
+            unfocused_secs: self.unfocused_secs,
+            distraction_events: self.distraction_events,
+            best_streak_secs: self.best_streak_secs,
+            acknowledged_distractions: self.ack_latencies_secs.len() as u32,
+            avg_ack_latency_secs: (!self.ack_latencies_secs.is_empty()).then(|| {
+                self.ack_latencies_secs.iter().sum::<f64>() / self.ack_latencies_secs.len() as f64
+            }),
+        }
+    }
+}
+
+// Totals for a finished focus session; time not focused or unfocused (drowsy, disconnected) is only in duration
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub started_at: String,
+    pub ended_at: String,
+    pub duration_secs: f64,
+    pub focused_secs: f64,
+    pub unfocused_secs: f64,
+    pub distraction_events: u32,
+    // Longest unbroken focused run
+    pub best_streak_secs: f64,
+    pub acknowledged_distractions: u32,
+    // Average time to refocus, measured from nudge to acknowledgment
+    pub avg_ack_latency_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreakInfo {
+    pub current_secs: f64,
+    pub best_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionRatio {
+    pub monitored_secs: f64,
+    pub connected_secs: f64,
+    pub ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub timestamp: String,
+    pub all_passed: bool,
+    pub client_count: usize,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+// How numeric metrics are rendered for display
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MetricsFormat {
+    pub decimals: u8,
+    pub show_units: bool,
+}
+
+impl Default for MetricsFormat {
+    fn default() -> Self {
+        Self { decimals: 1, show_units: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedMetrics {
+    pub timestamp: String,
+    pub attention: String,
+    pub brain_state: String,
+    pub head_orientation: String,
+    pub focus_score: String,
+    pub heart_rate: String,
+    pub movement_intensity: String,
+    pub theta_beta_ratio: String,
+}
+
+// Faults the monitor injects into its own polling (debug builds only)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaultConfig {
+    pub drop_rate: f64,
+    pub extra_latency_ms: u64,
+    pub force_disconnect: bool,
+}
+
+// Where the app keeps its config, history and logs on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct DataPaths {
+    pub config_dir: PathBuf,
+    pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+    pub db_path: PathBuf,
+    pub history_file: PathBuf,
+    pub log_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub http_server: bool,
+    pub websocket_server: bool,
+    pub extension_connected: bool,
+    pub messages_received: u32,
+    pub muse_connected: bool,
+    // Reconnect limit reached; polling stopped until reset_connection
+    pub connection_abandoned: bool,
+    pub started_at: String,
+    pub uptime_secs: f64,
+    // Messages received over the last minute
+    pub messages_per_minute: usize,
+    pub connected_clients: usize,
+    // Malformed bridge responses, which don't count toward disconnects
+    pub parse_errors: u64,
+}
+
+// Shared application state
+#[derive(Clone)]
+pub struct AppState {
+    pub ws_tx: broadcast::Sender<DuckMessage>,
+    pub message_count: Arc<Mutex<u32>>,
+    // Next DuckMessage sequence number
+    pub next_seq: Arc<AtomicU64>,
+    pub broadcast_order: Arc<Mutex<()>>,
+    // Arrival times of messages within MESSAGE_RATE_WINDOW
+    pub message_times: Arc<Mutex<VecDeque<std::time::Instant>>>,
+    pub tauri_handle: Arc<Mutex<Option<tauri::AppHandle>>>,
+    pub python_process: Arc<Mutex<Option<Child>>>,
+    // Times the supervisor has relaunched the Python backend
+    pub backend_restarts: Arc<Mutex<u32>>,
+    pub last_focus_state: Arc<Mutex<Option<String>>>,
+    pub last_state_change: Arc<Mutex<Option<std::time::Instant>>>,
+    pub muse_connected: Arc<Mutex<bool>>,
+    pub consecutive_failures: Arc<Mutex<u32>>,
+    // 200 responses from the bridge whose body couldn't be parsed
+    pub parse_error_count: Arc<Mutex<u64>>,
+    pub annotations: Arc<Mutex<VecDeque<Annotation>>>,
+    pub focus_transitions: Arc<Mutex<VecDeque<FocusTransition>>>,
+    pub metrics_history: Arc<Mutex<VecDeque<MetricsSample>>>,
+    pub log_lines: Arc<Mutex<VecDeque<LogLine>>>,
+    pub log_tx: broadcast::Sender<LogLine>,
+    pub log_stream_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    // Also broadcast each backend output line as a `backend_log` message
+    pub forward_backend_logs: Arc<Mutex<bool>>,
+    pub vote_config: Arc<Mutex<VoteConfig>>,
+    pub classification_window: Arc<Mutex<VecDeque<String>>>,
+    pub classification_mode: Arc<Mutex<ClassificationMode>>,
+    pub focus_hysteresis: Arc<Mutex<FocusHysteresis>>,
+    // Lowercased, trimmed substrings that mark an attention label as unfocused
+    pub unfocused_keywords: Arc<Mutex<Vec<String>>>,
+    pub fullscreen_active: Arc<Mutex<bool>>,
+    pub suppression_notified: Arc<Mutex<bool>>,
+    pub db: Arc<Mutex<Option<rusqlite::Connection>>>,
+    pub loop_stats: Arc<Mutex<LoopStats>>,
+    pub fault_config: Arc<Mutex<FaultConfig>>,
+    pub muse_schema: Arc<Mutex<MuseSchema>>,
+    pub detected_schema: Arc<Mutex<Option<MuseSchema>>>,
+    pub connection_uptime: Arc<Mutex<ConnectionUptime>>,
+    pub transition_log_path: Arc<Mutex<Option<PathBuf>>>,
+    pub metrics_format: Arc<Mutex<MetricsFormat>>,
+    pub simulation_active: Arc<Mutex<bool>>,
+    pub config_path: Option<PathBuf>,
+    pub remember_muse_port: bool,
+    pub last_muse_port: Arc<Mutex<Option<u16>>>,
+    pub last_processed_score: Arc<Mutex<Option<f64>>>,
+    pub change_start_score: Arc<Mutex<Option<f64>>>,
+    pub max_metrics_age_secs: Arc<Mutex<Option<f64>>>,
+    pub clock_offset_secs: Arc<Mutex<Option<f64>>>,
+    pub metrics_stale: Arc<Mutex<bool>>,
+    pub smoothed_score: Arc<Mutex<Option<f64>>>,
+    // EMA weight of the newest reading, in (0, 1]
+    pub score_smoothing_alpha: Arc<Mutex<f64>>,
+    pub score_alerts: Arc<Mutex<Vec<ScoreAlert>>>,
+    pub next_alert_id: Arc<Mutex<u64>>,
+    pub recording: Arc<Mutex<Option<Recording>>>,
+    pub port_selection: Arc<Mutex<PortSelection>>,
+    pub draining: Arc<AtomicBool>,
+    pub state_cooldowns: Arc<Mutex<StateCooldowns>>,
+    pub last_nudge_at: Arc<Mutex<HashMap<String, std::time::Instant>>>,
+    pub stability_window: Arc<Mutex<std::time::Duration>>,
+    // Sleep between monitor polls
+    pub poll_interval: Arc<Mutex<std::time::Duration>>,
+    // Times of unfocused transitions the user marked as wrong
+    pub false_positives: Arc<Mutex<Vec<chrono::DateTime<chrono::Utc>>>>,
+    pub http_client: reqwest::Client,
+    // Bridge port the monitor is currently polling
+    pub active_muse_port: Arc<Mutex<Option<u16>>>,
+    // Latest discovery probe of each port
+    pub port_probes: Arc<Mutex<HashMap<u16, PortProbe>>>,
+    // Failed discoveries since the last connection; None retries forever
+    pub max_reconnect_attempts: Arc<Mutex<Option<u32>>>,
+    pub reconnect_attempts: Arc<Mutex<u32>>,
+    pub reconnect_gave_up: Arc<Mutex<bool>>,
+    // Consecutive failed polls before declaring the headset disconnected
+    pub disconnect_threshold: Arc<Mutex<u32>>,
+    // Priority per msg_type, or per `msg_type:focus_state` for finer control
+    pub message_priorities: Arc<Mutex<HashMap<String, u8>>>,
+    // Present when DUCK_SYSLOG is set
+    pub syslog: Option<Arc<syslog::SyslogSink>>,
+    // Notification inbox of important messages
+    pub pending_alerts: Arc<Mutex<VecDeque<Alert>>>,
+    pub next_pending_alert_id: Arc<Mutex<u64>>,
+    pub alert_ttl_secs: Arc<Mutex<u64>>,
+    // Head turned away from center since, and whether that episode has nudged
+    pub look_away_since: Arc<Mutex<Option<std::time::Instant>>>,
+    pub look_away_notified: Arc<Mutex<bool>>,
+    // Alert conditions firing within this many ms are merged; 0 sends each at once
+    pub alert_merge_window_ms: Arc<Mutex<u64>>,
+    pub pending_nudge: Arc<Mutex<Option<PendingNudge>>>,
+    // Identifies the current monitoring session in the history database
+    pub session_id: Arc<Mutex<String>>,
+    pub session_tag: Arc<Mutex<Option<String>>>,
+    // Running session between start_session and stop_session
+    pub focus_session: Arc<Mutex<Option<FocusSession>>>,
+    // Most recently stopped session, kept for export
+    pub last_focus_session: Arc<Mutex<Option<FocusSession>>>,
+    // Distraction nudges sent but not yet acknowledged, by event_id
+    pub unacked_distractions: Arc<Mutex<VecDeque<(String, std::time::Instant)>>>,
+    pub display_connection: Arc<Mutex<DisplayConnection>>,
+    pub connection_status_gate: Arc<Mutex<ConnectionStatusGate>>,
+    pub connection_status_debounce: Arc<Mutex<std::time::Duration>>,
+    // Seconds between heartbeat broadcasts; 0 disables them
+    pub heartbeat_interval_secs: Arc<Mutex<u64>>,
+    pub connection_display_min_secs: Arc<Mutex<f64>>,
+    pub emit_retry_config: Arc<Mutex<EmitRetryConfig>>,
+    pub emit_retry: Arc<Mutex<EmitRetryState>>,
+    pub frontend_emit: Arc<Mutex<FrontendEmitHealth>>,
+    // Ports probed during discovery, and a port that bypasses it
+    pub muse_ports: Arc<Vec<u16>>,
+    pub forced_muse_port: Option<u16>,
+    // Most recent reading from the bridge; None before the first poll and after disconnect
+    pub latest_metrics: Arc<Mutex<Option<MuseMetrics>>>,
+    pub heart_rate_thresholds: Arc<Mutex<HeartRateThresholds>>,
+    // Last alerted heart rate zone, so alerts fire once per excursion
+    pub heart_rate_zone: Arc<Mutex<HeartRateZone>>,
+    pub movement_alert: Arc<Mutex<MovementAlertConfig>>,
+    // Current high-movement run, so each episode alerts once
+    pub fidget_episode: Arc<Mutex<Option<FidgetEpisode>>>,
+    // Connected WebSocket clients by client_id, for targeted messages
+    pub ws_clients: Arc<Mutex<HashMap<String, mpsc::Sender<DuckMessage>>>>,
+    // Flipped to true once to stop the server, monitor and WebSocket tasks
+    pub shutdown: Arc<watch::Sender<bool>>,
+    // Address the HTTP/WebSocket listener is bound to
+    pub server_port: u16,
+    pub server_host: IpAddr,
+    // From DUCK_API_TOKEN; None leaves /api/message open
+    pub api_token: Option<Arc<String>>,
+    pub started_at: std::time::Instant,
+    pub started_at_utc: chrono::DateTime<chrono::Utc>,
+}
+
+// Lock shared state even if another thread panicked while holding it; every
+// value here stays usable after a partial update, so one panic shouldn't take
+// down the monitor and every handler with it
+fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Tauri commands
+#[tauri::command]
+fn greet(name: &str) -> String {
+    format!("Hello, {}! You've been greeted from Rust!", name)
+}
+
+#[tauri::command]
+async fn get_service_status(state: tauri::State<'_, AppState>) -> Result<ServiceStatus, String> {
+    Ok(service_status(&state))
+}
+
+// Shared by get_service_status and GET /api/status
+fn service_status(state: &AppState) -> ServiceStatus {
+    let message_count = *lock_or_recover(&state.message_count);
+    let muse_connected = *lock_or_recover(&state.muse_connected);
+    let connected_clients = ws_client_count(state);
+    ServiceStatus {
+        http_server: true,
+        websocket_server: true,
+        extension_connected: connected_clients > 0,
+        messages_received: message_count,
+        muse_connected,
+        connection_abandoned: *lock_or_recover(&state.reconnect_gave_up),
+        started_at: state.started_at_utc.to_rfc3339(),
+        uptime_secs: state.started_at.elapsed().as_secs_f64(),
+        messages_per_minute: messages_per_minute(state),
+        connected_clients,
+        parse_errors: *lock_or_recover(&state.parse_error_count),
+    }
+}
+
+// Count a received message toward messages_received and the rate window
+fn record_message(state: &AppState) {
+    *lock_or_recover(&state.message_count) += 1;
+    let now = std::time::Instant::now();
+    let mut times = lock_or_recover(&state.message_times);
+    times.push_back(now);
+    prune_message_times(&mut times, now);
+}
+
+fn messages_per_minute(state: &AppState) -> usize {
+    let mut times = lock_or_recover(&state.message_times);
+    prune_message_times(&mut times, std::time::Instant::now());
+    times.len()
+}
+
+fn prune_message_times(times: &mut VecDeque<std::time::Instant>, now: std::time::Instant) {
+    while times
+        .front()
+        .is_some_and(|t| now.duration_since(*t) > MESSAGE_RATE_WINDOW)
+    {
+        times.pop_front();
+    }
+}
+
+// Full latest reading for live dashboards; None until the first poll
+#[tauri::command]
+async fn get_latest_metrics(state: tauri::State<'_, AppState>) -> Result<Option<MuseMetrics>, String> {
+    Ok(lock_or_recover(&state.latest_metrics).clone())
+}
+
+// Port the HTTP/WebSocket server bound to (3030 unless it was taken)
+#[tauri::command]
+async fn get_server_port(state: tauri::State<'_, AppState>) -> Result<u16, String> {
+    Ok(state.server_port)
+}
+
+// Lightweight alternative to get_service_status for frequent polling
+#[tauri::command]
+async fn get_client_count(state: tauri::State<'_, AppState>) -> Result<usize, String> {
+    Ok(ws_client_count(&state))
+}
+
+// Open WebSocket connections, the one source for every client count
+fn ws_client_count(state: &AppState) -> usize {
+    lock_or_recover(&state.ws_clients).len()
+}
+
+#[tauri::command]
+async fn get_annotations(state: tauri::State<'_, AppState>) -> Result<Vec<Annotation>, String> {
+    Ok(lock_or_recover(&state.annotations).iter().cloned().collect())
+}
+
+// Pearson correlation between heart rate and focus score over the history buffer
+#[tauri::command]
+async fn get_hr_focus_correlation(state: tauri::State<'_, AppState>) -> Result<HrFocusCorrelation, String> {
+    // 0 bpm means no PPG reading; raw history can also hold non-finite values
+    let (heart_rates, focus_scores): (Vec<f64>, Vec<f64>) = lock_or_recover(&state.metrics_history)
+        .iter()
+        .map(|s| (s.metrics.heart_rate, s.metrics.focus_score))
+        .filter(|(bpm, score)| bpm.is_finite() && *bpm > 0.0 && score.is_finite())
+        .unzip();
+
+    let sample_size = heart_rates.len();
+    let correlation = if sample_size >= MIN_CORRELATION_SAMPLES {
+        analytics::pearson_correlation(&heart_rates, &focus_scores)
+    } else {
+        None
+    };
+
+    Ok(HrFocusCorrelation { correlation, sample_size })
+}
+
+#[tauri::command]
+async fn get_backend_logs(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<LogLine>, String> {
+    let logs = lock_or_recover(&state.log_lines);
+    let limit = limit.unwrap_or(logs.len()).min(logs.len());
+    Ok(logs.iter().skip(logs.len() - limit).cloned().collect())
+}
+
+// Emit each new log line to the frontend as a `log_line` event
+#[tauri::command]
+async fn start_log_stream(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let mut task = lock_or_recover(&state.log_stream_task);
+    if task.as_ref().is_some_and(|t| !t.is_finished()) {
+        return Ok(());
+    }
+
+    let mut rx = state.log_tx.subscribe();
+    let stream_state = state.inner().clone();
+    *task = Some(tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(line) => {
+                    let _ = emit_to_frontend(&stream_state, "log_line", line);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    println!("⚠️ Log stream skipped {} lines", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }));
+
+    println!("📜 Log stream started");
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_log_stream(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if let Some(task) = lock_or_recover(&state.log_stream_task).take() {
+        task.abort();
+        println!("📜 Log stream stopped");
+    }
+    Ok(())
+}
+
+// Send backend output to the frontend and WebSocket clients as `backend_log` messages
+#[tauri::command]
+async fn set_backend_log_forwarding(state: tauri::State<'_, AppState>, enabled: bool) -> Result<(), String> {
+    *lock_or_recover(&state.forward_backend_logs) = enabled;
+    println!("🐍 Backend log forwarding: {}", if enabled { "on" } else { "off" });
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_vote_config(
+    state: tauri::State<'_, AppState>,
+    window: usize,
+    required: usize,
+) -> Result<VoteConfig, String> {
+    if window == 0 || window > 50 {
+        return Err("window must be between 1 and 50".to_string());
+    }
+    if required == 0 || required > window {
+        return Err(format!("required must be between 1 and {}", window));
+    }
+
+    let config = VoteConfig { window, required };
+    *lock_or_recover(&state.vote_config) = config;
+
+    // Drop votes that no longer fit in the window
+    let mut recent = lock_or_recover(&state.classification_window);
+    while recent.len() > window {
+        recent.pop_front();
+    }
+
+    println!("🗳️ Classification voting set to {}-of-{}", required, window);
+    Ok(config)
+}
+
+// Switch how readings are labelled; pending votes are dropped since they used the old mode
+#[tauri::command]
+async fn set_classification_mode(state: tauri::State<'_, AppState>, mode: ClassificationMode) -> Result<(), String> {
+    mode.validate()?;
+    *lock_or_recover(&state.classification_mode) = mode;
+    lock_or_recover(&state.classification_window).clear();
+    info!("🧠 Classification mode: {:?}", mode);
+    Ok(())
+}
+
+// Replace the attention keywords that map to unfocused; None restores the defaults.
+// Labels other than focused/neutral stay unfocused whatever the keywords.
+#[tauri::command]
+async fn set_unfocused_keywords(
+    state: tauri::State<'_, AppState>,
+    keywords: Option<Vec<String>>,
+) -> Result<Vec<String>, String> {
+    let keywords = match keywords {
+        Some(keywords) => {
+            let mut normalized: Vec<String> = Vec::new();
+            for keyword in keywords.iter().map(|k| k.trim().to_lowercase()).filter(|k| !k.is_empty()) {
+                if !normalized.contains(&keyword) {
+                    normalized.push(keyword);
+                }
+            }
+            if normalized.is_empty() {
+                return Err("keywords must contain at least one non-empty keyword".to_string());
+            }
+            normalized
+        }
+        None => default_unfocused_keywords(),
+    };
+    info!("🧠 Unfocused keywords: {:?}", keywords);
+    *lock_or_recover(&state.unfocused_keywords) = keywords.clone();
+    Ok(keywords)
+}
+
+#[tauri::command]
+async fn get_unfocused_keywords(state: tauri::State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(lock_or_recover(&state.unfocused_keywords).clone())
+}
+
+// Hysteresis band used by the focus_score classification mode
+#[tauri::command]
+async fn set_focus_thresholds(state: tauri::State<'_, AppState>, focus_enter: f64, focus_exit: f64) -> Result<(), String> {
+    let hysteresis = FocusHysteresis { focus_enter, focus_exit };
+    hysteresis.validate()?;
+    *lock_or_recover(&state.focus_hysteresis) = hysteresis;
+    info!("🧠 Focus thresholds: unfocused below {:.2}, focused above {:.2}", focus_enter, focus_exit);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_classification_mode(state: tauri::State<'_, AppState>) -> Result<ClassificationMode, String> {
+    Ok(*lock_or_recover(&state.classification_mode))
+}
+
+#[tauri::command]
+async fn get_vote_status(state: tauri::State<'_, AppState>) -> Result<VoteStatus, String> {
+    let config = *lock_or_recover(&state.vote_config);
+    let committed = lock_or_recover(&state.last_focus_state).clone();
+    let recent = lock_or_recover(&state.classification_window);
+    let (leading_state, leading_votes) = match leading_label(&recent, committed.as_deref()) {
+        Some((label, votes)) => (Some(label), votes),
+        None => (None, 0),
+    };
+
+    Ok(VoteStatus {
+        window: config.window,
+        required: config.required,
+        recent: recent.iter().cloned().collect(),
+        leading_state,
+        leading_votes,
+    })
+}
+
+#[tauri::command]
+async fn get_data_paths(app_handle: tauri::AppHandle) -> Result<DataPaths, String> {
+    resolve_data_paths(&app_handle)
+}
+
+// Focus summary of the last 7 days (today included) from the history database
+#[tauri::command]
+async fn get_weekly_digest(state: tauri::State<'_, AppState>) -> Result<WeeklyDigest, String> {
+    let db = lock_or_recover(&state.db);
+    let conn = db.as_ref().ok_or("History database unavailable")?;
+
+    let this_week = storage::daily_focus(conn, 6, 0).map_err(|e| e.to_string())?;
+    let last_week = storage::daily_focus(conn, 13, 7).map_err(|e| e.to_string())?;
+    let distraction_count = storage::count_distractions(conn, 6, 0).map_err(|e| e.to_string())?;
+
+    let total_focus_mins = this_week.iter().map(|d| d.focus_secs).sum::<f64>() / 60.0;
+    let days_with_data = this_week.len() as u32;
+    let avg_daily_focus = if days_with_data > 0 {
+        total_focus_mins / days_with_data as f64
+    } else {
+        0.0
+    };
+
+    let best_day = this_week
+        .iter()
+        .filter(|d| d.focus_secs > 0.0)
+        .max_by(|a, b| a.focus_secs.total_cmp(&b.focus_secs))
+        .map(|d| d.date.clone());
+
+    // Percent change in focus minutes, only when last week has something to compare to
+    let last_week_mins = last_week.iter().map(|d| d.focus_secs).sum::<f64>() / 60.0;
+    let trend_vs_last_week = (last_week_mins > 0.0)
+        .then(|| (total_focus_mins - last_week_mins) / last_week_mins * 100.0);
+
+    Ok(WeeklyDigest {
+        total_focus_mins,
+        avg_daily_focus,
+        best_day,
+        distraction_count,
+        trend_vs_last_week,
+        days_with_data,
+    })
+}
+
+#[tauri::command]
+async fn compare_to_yesterday(state: tauri::State<'_, AppState>) -> Result<DayComparison, String> {
+    let db = lock_or_recover(&state.db);
+    let conn = db.as_ref().ok_or("History database unavailable")?;
+
+    let today = storage::daily_focus(conn, 0, 0).map_err(|e| e.to_string())?.pop();
+    let yesterday = storage::daily_focus(conn, 1, 1).map_err(|e| e.to_string())?.pop();
+
+    let today_focus_mins = today.as_ref().map(|d| d.focus_secs / 60.0);
+    let yesterday_focus_mins = yesterday.as_ref().map(|d| d.focus_secs / 60.0);
+    Ok(DayComparison {
+        today_avg: today.as_ref().map(|d| d.avg_score),
+        yesterday_avg: yesterday.as_ref().map(|d| d.avg_score),
+        today_focus_mins,
+        yesterday_focus_mins,
+        delta: today_focus_mins.zip(yesterday_focus_mins).map(|(t, y)| t - y),
+    })
+}
+
+// Hours of the day ranked by historical focus, best first; empty until history exists
+#[tauri::command]
+async fn get_optimal_focus_times(state: tauri::State<'_, AppState>) -> Result<Vec<FocusTimeRecommendation>, String> {
+    let db = lock_or_recover(&state.db);
+    let conn = db.as_ref().ok_or("History database unavailable")?;
+    let hours = storage::hourly_focus(conn, OPTIMAL_TIMES_LOOKBACK_DAYS).map_err(|e| e.to_string())?;
+
+    let mut ranked: Vec<FocusTimeRecommendation> = hours
+        .into_iter()
+        .map(|h| {
+            let day_weight = (h.days as f64 / OPTIMAL_TIMES_FULL_CONFIDENCE_DAYS).min(1.0);
+            let sample_weight = (h.samples as f64 / OPTIMAL_TIMES_FULL_CONFIDENCE_SAMPLES).min(1.0);
+            FocusTimeRecommendation {
+                hour: h.hour,
+                avg_score: h.avg_score,
+                confidence: day_weight * sample_weight,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.avg_score.total_cmp(&a.avg_score));
+    Ok(ranked)
+}
+
+// Contiguous focus-state segments between two RFC 3339 times (`to` defaults to now).
+// A segment still in progress is extended to the current time.
+#[tauri::command]
+async fn get_timeline_segments(
+    state: tauri::State<'_, AppState>,
+    from: String,
+    to: Option<String>,
+) -> Result<Vec<TimelineSegment>, String> {
+    let parse = |value: &str| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|e| format!("Invalid timestamp '{}': {}", value, e))
+    };
+    let now = chrono::Utc::now();
+    let from = parse(&from)?;
+    let to = match to {
+        Some(to) => parse(&to)?,
+        None => now,
+    };
+    if from > to {
+        return Err("from must not be after to".to_string());
+    }
+
+    let samples = {
+        let db = lock_or_recover(&state.db);
+        let conn = db.as_ref().ok_or("History database unavailable")?;
+        let format = |t: chrono::DateTime<chrono::Utc>| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        storage::samples_between(conn, &format(from), &format(to)).map_err(|e| e.to_string())?
+    };
+    let parsed: Vec<_> = samples
+        .iter()
+        .filter_map(|s| parse(&s.timestamp).ok().map(|at| (at, s.focus_state.as_str(), s.focus_score)))
+        .collect();
+
+    let mut segments = analytics::state_segments(parsed, MAX_SAMPLE_GAP_SECS);
+    if let Some(last) = segments.last_mut() {
+        let ongoing = to >= now
+            && *lock_or_recover(&state.muse_connected)
+            && (now - last.end).num_milliseconds() as f64 / 1000.0 <= MAX_SAMPLE_GAP_SECS;
+        if ongoing {
+            last.end = now;
+        }
+    }
+
+    Ok(segments
+        .into_iter()
+        .map(|s| TimelineSegment {
+            state: s.state,
+            start: s.start.to_rfc3339(),
+            end: s.end.to_rfc3339(),
+            avg_score: s.avg_score,
+        })
+        .collect())
+}
+
+#[tauri::command]
+async fn get_loop_timing(state: tauri::State<'_, AppState>) -> Result<LoopTiming, String> {
+    let stats = lock_or_recover(&state.loop_stats);
+    let avg_iteration_ms = if stats.recent_ms.is_empty() {
+        0.0
+    } else {
+        stats.recent_ms.iter().sum::<f64>() / stats.recent_ms.len() as f64
+    };
+
+    Ok(LoopTiming {
+        avg_iteration_ms,
+        max_iteration_ms: stats.max_ms,
+        target_ms: lock_or_recover(&state.poll_interval).as_millis() as u64,
+        overruns: stats.overruns,
+    })
+}
+
+// Make the monitor drop fetches, add latency or fail every poll, to exercise
+// failure handling and reconnection without real network trouble
+#[tauri::command]
+async fn set_fault_injection(
+    state: tauri::State<'_, AppState>,
+    config: FaultConfig,
+) -> Result<(), String> {
+    if !cfg!(debug_assertions) {
+        return Err("Fault injection is only available in debug builds".to_string());
+    }
+    if !(0.0..=1.0).contains(&config.drop_rate) {
+        return Err("drop_rate must be between 0 and 1".to_string());
+    }
+
+    println!("💉 Fault injection set: {:?}", config);
+    *lock_or_recover(&state.fault_config) = config;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_connection_ratio(state: tauri::State<'_, AppState>) -> Result<ConnectionRatio, String> {
+    let uptime = lock_or_recover(&state.connection_uptime);
+    let ratio = if uptime.monitored_secs > 0.0 {
+        uptime.connected_secs / uptime.monitored_secs
+    } else {
+        0.0
+    };
+
+    Ok(ConnectionRatio {
+        monitored_secs: uptime.monitored_secs,
+        connected_secs: uptime.connected_secs,
+        ratio,
+    })
+}
+
+// Clear in-memory session data (history, transitions, annotations, uptime)
+#[tauri::command]
+async fn reset_session(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    lock_or_recover(&state.metrics_history).clear();
+    lock_or_recover(&state.focus_transitions).clear();
+    lock_or_recover(&state.annotations).clear();
+    lock_or_recover(&state.last_nudge_at).clear();
+    lock_or_recover(&state.false_positives).clear();
+    *lock_or_recover(&state.connection_uptime) = ConnectionUptime::default();
+    *lock_or_recover(&state.session_id) = new_session_id();
+    println!("🔄 Session data reset");
+    Ok(())
+}
+
+// In-memory buffers that can be inspected and cleared individually
+const BUFFER_NAMES: &[&str] = &["metrics_history", "focus_transitions", "backend_logs", "annotations", "pending_alerts"];
+
+fn unknown_buffer(name: &str) -> String {
+    format!("Unknown buffer '{}' (expected one of: {})", name, BUFFER_NAMES.join(", "))
+}
+
+// Current length and capacity of a named buffer
+fn buffer_info(state: &AppState, name: &str) -> Result<BufferInfo, String> {
+    let (len, capacity) = match name {
+        "metrics_history" => (lock_or_recover(&state.metrics_history).len(), METRICS_HISTORY_SIZE),
+        "focus_transitions" => (lock_or_recover(&state.focus_transitions).len(), TRANSITION_HISTORY_SIZE),
+        "backend_logs" => (lock_or_recover(&state.log_lines).len(), LOG_BUFFER_SIZE),
+        "annotations" => (lock_or_recover(&state.annotations).len(), ANNOTATION_BUFFER_SIZE),
+        "pending_alerts" => (lock_or_recover(&state.pending_alerts).len(), PENDING_ALERTS_SIZE),
+        _ => return Err(unknown_buffer(name)),
+    };
+    Ok(BufferInfo { name: name.to_string(), len, capacity })
+}
+
+#[tauri::command]
+async fn list_buffers(state: tauri::State<'_, AppState>) -> Result<Vec<BufferInfo>, String> {
+    BUFFER_NAMES.iter().map(|name| buffer_info(&state, name)).collect()
+}
+
+// Empty one buffer, returning how many entries were removed
+#[tauri::command]
+async fn clear_buffer(state: tauri::State<'_, AppState>, name: String) -> Result<usize, String> {
+    fn drain<T>(buffer: &Mutex<VecDeque<T>>) -> usize {
+        let mut buffer = lock_or_recover(buffer);
+        let removed = buffer.len();
+        buffer.clear();
+        removed
+    }
+
+    let removed = match name.as_str() {
+        "metrics_history" => drain(&state.metrics_history),
+        "focus_transitions" => drain(&state.focus_transitions),
+        "backend_logs" => drain(&state.log_lines),
+        "annotations" => drain(&state.annotations),
+        "pending_alerts" => drain(&state.pending_alerts),
+        _ => return Err(unknown_buffer(&name)),
+    };
+    println!("🧹 Cleared {} ({} entries)", name, removed);
+    Ok(removed)
+}
+
+// Label what the user is doing (e.g. "coding"); starts a new session in the history
+#[tauri::command]
+async fn set_session_tag(state: tauri::State<'_, AppState>, tag: Option<String>) -> Result<(), String> {
+    let tag = tag.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let mut current = lock_or_recover(&state.session_tag);
+    if *current == tag {
+        return Ok(());
+    }
+    match &tag {
+        Some(t) => println!("🏷️ Session tag: {}", t),
+        None => println!("🏷️ Session tag cleared"),
+    }
+    *current = tag;
+    *lock_or_recover(&state.session_id) = new_session_id();
+    Ok(())
+}
+
+// Focus totals per session tag from the history database, busiest first
+#[tauri::command]
+async fn get_stats_by_tag(state: tauri::State<'_, AppState>) -> Result<Vec<storage::TagStats>, String> {
+    let db = lock_or_recover(&state.db);
+    let conn = db.as_ref().ok_or("History database unavailable")?;
+    storage::stats_by_tag(conn, UNTAGGED_SESSION_LABEL).map_err(|e| e.to_string())
+}
+
+// Begin timing a focus session from the current focus state
+#[tauri::command]
+async fn start_session(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let mut session = lock_or_recover(&state.focus_session);
+    if session.is_some() {
+        return Err("A focus session is already running".to_string());
+    }
+    let current_state = lock_or_recover(&state.last_focus_state).clone();
+    *session = Some(FocusSession::new(current_state, std::time::Instant::now()));
+    println!("⏱️ Focus session started");
+    Ok(())
+}
+
+// End the running focus session and return its totals
+#[tauri::command]
+async fn stop_session(state: tauri::State<'_, AppState>) -> Result<SessionSummary, String> {
+    let mut session = lock_or_recover(&state.focus_session)
+        .take()
+        .ok_or("No focus session is running")?;
+    let summary = session.finish(std::time::Instant::now());
+    *lock_or_recover(&state.last_focus_session) = Some(session);
+    println!(
+        "⏱️ Focus session ended: {:.0}s, {:.0}s focused, {} distractions",
+        summary.duration_secs, summary.focused_secs, summary.distraction_events
+    );
+    Ok(summary)
+}
+
+// Kill leftover backend processes from earlier runs (not the one we manage);
+// returns their PIDs
+#[tauri::command]
+async fn kill_stale_backends(state: tauri::State<'_, AppState>) -> Result<Vec<u32>, String> {
+    let managed = lock_or_recover(&state.python_process).as_ref().map(Child::id);
+    let app_handle = lock_or_recover(&state.tauri_handle).clone();
+    kill_stale_python_backends(app_handle.as_ref(), managed)
+}
+
+// Focus streak in the running session; zeros when no session is active
+#[tauri::command]
+async fn get_current_streak(state: tauri::State<'_, AppState>) -> Result<StreakInfo, String> {
+    Ok(match lock_or_recover(&state.focus_session).as_ref() {
+        Some(session) => session.streak(std::time::Instant::now()),
+        None => StreakInfo { current_secs: 0.0, best_secs: 0.0 },
+    })
+}
+
+// The user noticed a distraction nudge; returns seconds since it was sent
+#[tauri::command]
+async fn acknowledge_distraction(state: tauri::State<'_, AppState>, event_id: String) -> Result<f64, String> {
+    let sent = {
+        let mut unacked = lock_or_recover(&state.unacked_distractions);
+        let index = unacked
+            .iter()
+            .position(|(id, _)| *id == event_id)
+            .ok_or_else(|| format!("No unacknowledged distraction with id {}", event_id))?;
+        unacked.remove(index).map(|(_, sent)| sent)
+    };
+    let latency = sent.map_or(0.0, |sent| sent.elapsed().as_secs_f64());
+
+    if let Some(session) = lock_or_recover(&state.focus_session).as_mut() {
+        session.ack_latencies_secs.push(latency);
+    }
+    info!("👍 Distraction {} acknowledged after {:.1}s", event_id, latency);
+    Ok(latency)
+}
+
+// Write the state changes of the running session, or else the last stopped one, as CSV
+#[tauri::command]
+async fn export_session_csv(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let transitions = {
+        let running = lock_or_recover(&state.focus_session);
+        let last = lock_or_recover(&state.last_focus_session);
+        running
+            .as_ref()
+            .or(last.as_ref())
+            .map(|session| session.transitions.clone())
+            .ok_or("No focus session to export")?
+    };
+
+    let path = PathBuf::from(path);
+    write_session_csv(&path, &transitions).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    info!("📄 Exported {} session rows to {}", transitions.len(), path.display());
+    Ok(())
+}
+
+fn write_session_csv(path: &std::path::Path, transitions: &[SessionTransition]) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(writer, "timestamp,focus_state,focus_score,heart_rate,theta_beta_ratio,movement_intensity")?;
+    for row in transitions {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            row.timestamp,
+            row.focus_state,
+            row.focus_score,
+            row.heart_rate,
+            row.theta_beta_ratio,
+            row.movement_intensity
+        )?;
+    }
+    writer.flush()
+}
+
+fn new_session_id() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+// Append each focus transition as a readable line to `path`; None turns it off
+#[tauri::command]
+async fn set_transition_log(state: tauri::State<'_, AppState>, path: Option<String>) -> Result<(), String> {
+    let path = path.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+    if let Some(parent) = path.as_ref().and_then(|p| p.parent()) {
+        if !parent.as_os_str().is_empty() && !parent.is_dir() {
+            return Err(format!("Directory does not exist: {}", parent.display()));
+        }
+    }
+
+    match &path {
+        Some(p) => println!("📓 Logging focus transitions to {}", p.display()),
+        None => println!("📓 Focus transition log disabled"),
+    }
+    *lock_or_recover(&state.transition_log_path) = path;
+    Ok(())
+}
+
+// One-shot health check of every subsystem, independent of the monitor loop
+#[tauri::command]
+async fn run_diagnostics(state: tauri::State<'_, AppState>) -> Result<DiagnosticsReport, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(2))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut checks = Vec::new();
+
+    // Python subprocess
+    let python_check = match lock_or_recover(&state.python_process).as_mut() {
+        Some(child) => match child.try_wait() {
+            Ok(None) => (true, format!("Running (PID {})", child.id())),
+            Ok(Some(status)) => (false, format!("Exited: {}", status)),
+            Err(e) => (false, format!("Cannot query process: {}", e)),
+        },
+        None => (false, "Not launched by the app (external backend?)".to_string()),
+    };
+    checks.push(DiagnosticCheck {
+        name: "python_process".to_string(),
+        passed: python_check.0,
+        detail: python_check.1,
+    });
+
+    // Muse API on each port, keeping the first healthy body for the parse check
+    let mut first_body: Option<(u16, serde_json::Value)> = None;
+    let ports = state
+        .forced_muse_port
+        .into_iter()
+        .chain(state.muse_ports.iter().copied().filter(|&p| Some(p) != state.forced_muse_port));
+    for port in ports {
+        let url = format!("http://localhost:{}/api/metrics", port);
+        let (passed, detail) = match client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                if first_body.is_none() {
+                    if let Ok(body) = response.json::<serde_json::Value>().await {
+                        first_body = Some((port, body));
+                    }
+                }
+                (true, "Reachable".to_string())
+            }
+            Ok(response) => (false, format!("HTTP {}", response.status())),
+            Err(e) => (false, e.to_string()),
+        };
+        checks.push(DiagnosticCheck {
+            name: format!("muse_api:{}", port),
+            passed,
+            detail,
+        });
+    }
+
+    // Metrics parse
+    let parse_check = match &first_body {
+        Some((port, body)) => match parse_metrics(&state, body) {
+            Some(metrics) => (true, format!("Port {}: attention={}, focus_score={:.2}", port, metrics.attention, metrics.focus_score)),
+            None => (false, format!("Port {}: response did not match any known schema", port)),
+        },
+        None => (false, "No Muse API response to parse".to_string()),
+    };
+    checks.push(DiagnosticCheck {
+        name: "metrics_parse".to_string(),
+        passed: parse_check.0,
+        detail: parse_check.1,
+    });
+
+    // Our own HTTP/WebSocket server
+    let server_addr = SocketAddr::new(reachable_host(state.server_host), state.server_port);
+    let server_check = match client.get(format!("http://{}/health", server_addr)).send().await {
+        Ok(response) if response.status().is_success() => (true, format!("Listening on {}", server_addr)),
+        Ok(response) => (false, format!("Health check returned HTTP {}", response.status())),
+        Err(e) => (false, e.to_string()),
+    };
+    checks.push(DiagnosticCheck {
+        name: "websocket_server".to_string(),
+        passed: server_check.0,
+        detail: server_check.1,
+    });
+
+    let client_count = ws_client_count(&state);
+    checks.push(DiagnosticCheck {
+        name: "websocket_clients".to_string(),
+        passed: client_count > 0,
+        detail: format!("{} connected", client_count),
+    });
+
+    Ok(DiagnosticsReport {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        all_passed: checks.iter().all(|c| c.passed),
+        client_count,
+        checks,
+    })
+}
+
+#[tauri::command]
+async fn set_metrics_format(
+    state: tauri::State<'_, AppState>,
+    decimals: u8,
+    show_units: bool,
+) -> Result<(), String> {
+    if decimals > 4 {
+        return Err("decimals must be between 0 and 4".to_string());
+    }
+    *lock_or_recover(&state.metrics_format) = MetricsFormat { decimals, show_units };
+    Ok(())
+}
+
+// Latest metrics rendered with the configured rounding and unit labels
+#[tauri::command]
+async fn get_formatted_metrics(state: tauri::State<'_, AppState>) -> Result<Option<FormattedMetrics>, String> {
+    let format = *lock_or_recover(&state.metrics_format);
+    let Some(metrics) = lock_or_recover(&state.latest_metrics).clone() else {
+        return Ok(None);
+    };
+    // The bridge's own capture time when it sends one
+    let timestamp = metrics
+        .captured_at
+        .and_then(|secs| chrono::DateTime::from_timestamp_millis((secs * 1000.0) as i64))
+        .unwrap_or_else(chrono::Utc::now)
+        .to_rfc3339();
+
+    let number = |value: f64, unit: &str| {
+        let rounded = format!("{:.*}", format.decimals as usize, value);
+        if format.show_units && !unit.is_empty() {
+            format!("{} {}", rounded, unit)
+        } else {
+            rounded
+        }
+    };
+
+    Ok(Some(FormattedMetrics {
+        timestamp,
+        attention: metrics.attention.clone(),
+        brain_state: metrics.brain_state.clone(),
+        head_orientation: metrics.head_orientation.clone(),
+        focus_score: number(metrics.focus_score, ""),
+        heart_rate: number(metrics.heart_rate, "bpm"),
+        movement_intensity: number(metrics.movement_intensity, ""),
+        theta_beta_ratio: number(metrics.theta_beta_ratio, ""),
+    }))
+}
+
+// Feed a linear focus_score ramp through the classification pipeline, one sample per
+// poll interval, to observe when the duck reacts. Live readings pause meanwhile.
+#[tauri::command]
+async fn simulate_decline(
+    state: tauri::State<'_, AppState>,
+    from: f64,
+    to: f64,
+    duration_secs: u64,
+) -> Result<u64, String> {
+    if !(0.0..=1.0).contains(&from) || !(0.0..=1.0).contains(&to) {
+        return Err("from and to must be between 0 and 1".to_string());
+    }
+    if duration_secs == 0 || duration_secs > 600 {
+        return Err("duration_secs must be between 1 and 600".to_string());
+    }
+
+    {
+        let mut active = lock_or_recover(&state.simulation_active);
+        if *active {
+            return Err("A simulation is already running".to_string());
+        }
+        *active = true;
+    }
+
+    let interval = *lock_or_recover(&state.poll_interval);
+    let steps = (duration_secs * 1000 / interval.as_millis() as u64).max(1);
+    let state = state.inner().clone();
+    println!("🧪 Simulating focus {:.2} → {:.2} over {}s", from, to, duration_secs);
+
+    tokio::spawn(async move {
+        for step in 0..=steps {
+            let score = from + (to - from) * step as f64 / steps as f64;
+            process_metrics(&state, &synthetic_metrics(score), std::time::Instant::now());
+            tokio::time::sleep(interval).await;
+        }
+        *lock_or_recover(&state.simulation_active) = false;
+        println!("🧪 Simulation finished");
+    });
+
+    Ok(steps + 1)
+}
+
+// Send a focus_state_change for "focused" or "unfocused" as if the monitor had
+// committed it, so the frontend can be exercised without a headset
+#[tauri::command]
+async fn simulate_focus_state(state: tauri::State<'_, AppState>, focus_state: String) -> Result<(), String> {
+    let focus_state = focus_state.trim().to_lowercase();
+    let score = match focus_state.as_str() {
+        "focused" => 0.8,
+        "unfocused" => 0.2,
+        other => return Err(format!("Unknown focus state '{}', expected 'focused' or 'unfocused'", other)),
+    };
+
+    // Real readings continue from the simulated state rather than re-sending it
+    *lock_or_recover(&state.last_focus_state) = Some(focus_state.clone());
+    *lock_or_recover(&state.last_state_change) = None;
+
+    info!("🧪 Simulating focus state: {}", focus_state);
+    let metrics = synthetic_metrics(score);
+    dispatch_focus_change(&state, focus_change_message(&focus_state, &metrics), &metrics);
+    Ok(())
+}
+
+// Time the per-sample decision steps (smoothing, voting, state mapping) on synthetic
+// readings, without touching live state
+#[tauri::command]
+async fn benchmark_pipeline(state: tauri::State<'_, AppState>, iterations: u32) -> Result<PipelineBenchmark, String> {
+    if iterations == 0 || iterations > MAX_BENCHMARK_ITERATIONS {
+        return Err(format!("iterations must be between 1 and {}", MAX_BENCHMARK_ITERATIONS));
+    }
+    let config = *lock_or_recover(&state.vote_config);
+    let keywords = lock_or_recover(&state.unfocused_keywords).clone();
+    let alpha = *lock_or_recover(&state.score_smoothing_alpha);
+
+    let elapsed = tokio::task::spawn_blocking(move || {
+        // A focus sweep so voting sees label changes
+        let samples: Vec<MuseMetrics> = (0..100).map(|i| synthetic_metrics(i as f64 / 99.0)).collect();
+        let mut window = VecDeque::with_capacity(config.window + 1);
+        let mut smoothed = None;
+        let mut committed: Option<String> = None;
+
+        let start = std::time::Instant::now();
+        for i in 0..iterations as usize {
+            let metrics = &samples[i % samples.len()];
+            smoothed = Some(smooth_score(smoothed, metrics.focus_score, alpha));
+            let voted = vote_classification(&mut window, config, &metrics.attention, committed.as_deref());
+            std::hint::black_box(voted.as_deref().map(|label| focus_state_for(label, &keywords)));
+            if voted.is_some() {
+                committed = voted;
+            }
+        }
+        std::hint::black_box(smoothed);
+        start.elapsed()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let avg_ns_per_sample = elapsed.as_nanos() as f64 / iterations as f64;
+    let samples_per_sec = if avg_ns_per_sample > 0.0 { 1e9 / avg_ns_per_sample } else { f64::INFINITY };
+    println!("⏱️ Pipeline benchmark: {:.0} ns/sample over {} iterations", avg_ns_per_sample, iterations);
+    Ok(PipelineBenchmark { iterations, avg_ns_per_sample, samples_per_sec })
+}
+
+// Skip classification of readings whose bridge timestamp is older than `secs`; None disables
+#[tauri::command]
+async fn set_max_metrics_age(state: tauri::State<'_, AppState>, secs: Option<f64>) -> Result<(), String> {
+    if let Some(secs) = secs {
+        if !secs.is_finite() || secs <= 0.0 {
+            return Err("secs must be a positive number".to_string());
+        }
+    }
+    *lock_or_recover(&state.max_metrics_age_secs) = secs;
+    *lock_or_recover(&state.metrics_stale) = false;
+    Ok(())
+}
+
+// Focus as a 0-100 integer for progress-ring widgets; 0 until metrics arrive
+#[tauri::command]
+async fn get_focus_percent(state: tauri::State<'_, AppState>) -> Result<u8, String> {
+    let smoothed = lock_or_recover(&state.smoothed_score).unwrap_or(0.0);
+    Ok((smoothed * 100.0).round().clamp(0.0, 100.0) as u8)
+}
+
+// Slope of the smoothed focus score in points per second over the last few seconds;
+// positive is improving. None until enough recent readings exist.
+#[tauri::command]
+async fn get_focus_velocity(state: tauri::State<'_, AppState>) -> Result<Option<f64>, String> {
+    let alpha = *lock_or_recover(&state.score_smoothing_alpha);
+    let history = lock_or_recover(&state.metrics_history);
+    let now = chrono::Utc::now();
+    let secs_ago = |at: chrono::DateTime<chrono::Utc>| (now - at).num_milliseconds() as f64 / 1000.0;
+
+    let recent: Vec<&MetricsSample> = history
+        .iter()
+        .filter(|s| secs_ago(s.at) <= FOCUS_VELOCITY_WINDOW_SECS && s.metrics.focus_score.is_finite())
+        .collect();
+    if recent.len() < MIN_VELOCITY_SAMPLES {
+        return Ok(None);
+    }
+
+    // Smooth the window the same way the live score is smoothed
+    let mut smoothed = None;
+    let points: Vec<(f64, f64)> = recent
+        .iter()
+        .map(|s| {
+            let score = smooth_score(smoothed, s.metrics.focus_score, alpha);
+            smoothed = Some(score);
+            (-secs_ago(s.at), score)
+        })
+        .collect();
+
+    Ok(analytics::linear_slope(&points))
+}
+
+// Arm a one-shot alert for when the smoothed score next crosses `threshold`
+#[tauri::command]
+async fn set_score_alert(
+    state: tauri::State<'_, AppState>,
+    threshold: f64,
+    direction: String,
+) -> Result<u64, String> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err("threshold must be between 0 and 1".to_string());
+    }
+    let direction = match direction.trim().to_lowercase().as_str() {
+        "above" => AlertDirection::Above,
+        "below" => AlertDirection::Below,
+        other => return Err(format!("Unknown direction '{}', expected 'above' or 'below'", other)),
+    };
+
+    let id = {
+        let mut next_id = lock_or_recover(&state.next_alert_id);
+        *next_id += 1;
+        *next_id
+    };
+    lock_or_recover(&state.score_alerts).push(ScoreAlert { id, threshold, direction });
+    println!("🔔 Score alert {} armed: {:?} {:.2}", id, direction, threshold);
+    Ok(id)
+}
+
+#[tauri::command]
+async fn get_score_alerts(state: tauri::State<'_, AppState>) -> Result<Vec<ScoreAlert>, String> {
+    Ok(lock_or_recover(&state.score_alerts).clone())
+}
+
+#[tauri::command]
+async fn cancel_score_alert(state: tauri::State<'_, AppState>, id: u64) -> Result<(), String> {
+    let mut alerts = lock_or_recover(&state.score_alerts);
+    let before = alerts.len();
+    alerts.retain(|alert| alert.id != id);
+    if alerts.len() == before {
+        return Err(format!("No score alert with id {}", id));
+    }
+    Ok(())
+}
+
+// Important messages not yet acknowledged, oldest first
+#[tauri::command]
+async fn get_pending_alerts(state: tauri::State<'_, AppState>) -> Result<Vec<Alert>, String> {
+    let mut alerts = lock_or_recover(&state.pending_alerts);
+    let now = chrono::Utc::now();
+    alerts.retain(|alert| alert.expires > now);
+    Ok(alerts.iter().cloned().collect())
+}
+
+#[tauri::command]
+async fn acknowledge_alert(state: tauri::State<'_, AppState>, id: u64) -> Result<(), String> {
+    let mut alerts = lock_or_recover(&state.pending_alerts);
+    let before = alerts.len();
+    alerts.retain(|alert| alert.id != id);
+    if alerts.len() == before {
+        return Err(format!("No pending alert with id {}", id));
+    }
+    Ok(())
+}
+
+// Merge alerts (distraction, look-away) that fire within `ms` of each other; 0 disables merging
+#[tauri::command]
+async fn set_alert_merge_window(state: tauri::State<'_, AppState>, ms: u64) -> Result<(), String> {
+    if ms > MAX_ALERT_MERGE_WINDOW_MS {
+        return Err(format!("ms must be at most {}", MAX_ALERT_MERGE_WINDOW_MS));
+    }
+    *lock_or_recover(&state.alert_merge_window_ms) = ms;
+    Ok(())
+}
+
+// Connection indicator state for the UI, smoothed by the minimum display time
+#[tauri::command]
+async fn get_display_connection_state(state: tauri::State<'_, AppState>) -> Result<DisplayConnectionStatus, String> {
+    let display = lock_or_recover(&state.display_connection);
+    Ok(DisplayConnectionStatus {
+        state: display.state.to_string(),
+        shown_for_secs: display.since.elapsed().as_secs_f64(),
+    })
+}
+
+#[tauri::command]
+async fn set_connection_display_min_secs(state: tauri::State<'_, AppState>, secs: f64) -> Result<(), String> {
+    if !(0.0..=MAX_CONNECTION_DISPLAY_MIN_SECS).contains(&secs) {
+        return Err(format!("secs must be between 0 and {}", MAX_CONNECTION_DISPLAY_MIN_SECS));
+    }
+    *lock_or_recover(&state.connection_display_min_secs) = secs;
+    Ok(())
+}
+
+// Minimum seconds between connection_status broadcasts; 0 sends every change
+#[tauri::command]
+async fn set_connection_status_debounce(state: tauri::State<'_, AppState>, secs: f64) -> Result<(), String> {
+    if !(0.0..=MAX_CONNECTION_STATUS_DEBOUNCE_SECS).contains(&secs) {
+        return Err(format!("secs must be between 0 and {}", MAX_CONNECTION_STATUS_DEBOUNCE_SECS));
+    }
+    *lock_or_recover(&state.connection_status_debounce) = std::time::Duration::from_secs_f64(secs);
+    Ok(())
+}
+
+// Seconds between heartbeat messages; 0 stops them
+#[tauri::command]
+async fn set_heartbeat_interval(state: tauri::State<'_, AppState>, secs: u64) -> Result<(), String> {
+    if secs > MAX_HEARTBEAT_INTERVAL_SECS {
+        return Err(format!("secs must be at most {}", MAX_HEARTBEAT_INTERVAL_SECS));
+    }
+    *lock_or_recover(&state.heartbeat_interval_secs) = secs;
+    Ok(())
+}
+
+// Retry failed frontend emits of `msg_types` up to `max_attempts` times; 0 disables retries
+#[tauri::command]
+async fn set_emit_retry(state: tauri::State<'_, AppState>, config: EmitRetryConfig) -> Result<(), String> {
+    if config.max_attempts > MAX_EMIT_RETRY_ATTEMPTS {
+        return Err(format!("max_attempts must be at most {}", MAX_EMIT_RETRY_ATTEMPTS));
+    }
+    *lock_or_recover(&state.emit_retry_config) = config;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_emit_retry_stats(state: tauri::State<'_, AppState>) -> Result<EmitRetryStats, String> {
+    let retry = lock_or_recover(&state.emit_retry);
+    Ok(EmitRetryStats {
+        queued: retry.queue.len(),
+        retried: retry.retried,
+        dropped: retry.dropped,
+        frontend_suspended: lock_or_recover(&state.frontend_emit).suspended,
+    })
+}
+
+// How long new alerts stay pending when not acknowledged
+#[tauri::command]
+async fn set_alert_ttl(state: tauri::State<'_, AppState>, secs: u64) -> Result<(), String> {
+    if secs == 0 || secs > MAX_ALERT_TTL_SECS {
+        return Err(format!("secs must be between 1 and {}", MAX_ALERT_TTL_SECS));
+    }
+    *lock_or_recover(&state.alert_ttl_secs) = secs;
+    Ok(())
+}
+
+// Write every broadcast message (and optionally raw metrics) to `path` as JSON lines
+#[tauri::command]
+async fn start_recording(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    include_metrics: Option<bool>,
+) -> Result<(), String> {
+    let mut recording = lock_or_recover(&state.recording);
+    if let Some(active) = recording.as_ref() {
+        return Err(format!("Already recording to {}", active.path.display()));
+    }
+
+    let path = PathBuf::from(path);
+    let file = std::fs::File::create(&path)
+        .map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+
+    // broadcast() writes each message; a channel subscription would count as a client
+    println!("⏺️ Recording broadcast stream to {}", path.display());
+    *recording = Some(Recording {
+        path,
+        include_metrics: include_metrics.unwrap_or(false),
+        writer: io::BufWriter::new(file),
+        lines: 0,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_recording(state: tauri::State<'_, AppState>) -> Result<RecordingSummary, String> {
+    let mut recording = lock_or_recover(&state.recording)
+        .take()
+        .ok_or("No recording in progress")?;
+
+    recording
+        .writer
+        .flush()
+        .map_err(|e| format!("Failed to flush {}: {}", recording.path.display(), e))?;
+
+    println!("⏹️ Recording stopped ({} lines)", recording.lines);
+    Ok(RecordingSummary {
+        path: recording.path,
+        lines: recording.lines,
+    })
+}
+
+// The newest `limit` full readings, oldest first, as stored after validation
+#[tauri::command]
+async fn get_full_samples(state: tauri::State<'_, AppState>, limit: usize) -> Result<Vec<MetricsSample>, String> {
+    let history = lock_or_recover(&state.metrics_history);
+    let limit = limit.min(history.len());
+    Ok(history.iter().skip(history.len() - limit).cloned().collect())
+}
+
+// Heuristic guidance for getting a good reading, from recent samples
+#[tauri::command]
+async fn diagnose_headset(state: tauri::State<'_, AppState>) -> Result<HeadsetDiagnosis, String> {
+    let connected = *lock_or_recover(&state.muse_connected);
+    let stale = *lock_or_recover(&state.metrics_stale);
+    let recent: Vec<MuseMetrics> = lock_or_recover(&state.metrics_history)
+        .iter()
+        .rev()
+        .take(HEADSET_DIAGNOSIS_WINDOW)
+        .map(|s| s.metrics.clone())
+        .collect();
+
+    Ok(diagnose_samples(connected, stale, &recent))
+}
+
+// Average time from a distraction to the following focus recovery
+#[tauri::command]
+async fn get_avg_refocus_time(state: tauri::State<'_, AppState>) -> Result<RefocusStats, String> {
+    let transitions = lock_or_recover(&state.focus_transitions);
+    let durations = analytics::refocus_durations(
+        transitions.iter().map(|t| (t.at, t.focus_state.as_str())),
+    );
+
+    let count = durations.len();
+    let avg_refocus_secs = (count > 0).then(|| durations.iter().sum::<f64>() / count as f64);
+    Ok(RefocusStats { avg_refocus_secs, count })
+}
+
+// Switches to unfocused per hour over the trailing window (default 1 hour), from
+// the transition history. Shortly after startup only the time since launch counts.
+#[tauri::command]
+async fn get_distraction_rate(state: tauri::State<'_, AppState>, window_secs: Option<f64>) -> Result<f64, String> {
+    let window_secs = window_secs.unwrap_or(DEFAULT_DISTRACTION_RATE_WINDOW_SECS);
+    if !window_secs.is_finite() || window_secs <= 0.0 {
+        return Err("window_secs must be a positive number".to_string());
+    }
+
+    let since = chrono::Utc::now() - chrono::Duration::milliseconds((window_secs * 1000.0) as i64);
+    let events = lock_or_recover(&state.focus_transitions)
+        .iter()
+        .filter(|t| t.at >= since && t.focus_state == "unfocused")
+        .count();
+    if events == 0 {
+        return Ok(0.0);
+    }
+
+    let observed_secs = window_secs.min(state.started_at.elapsed().as_secs_f64()).max(1.0);
+    Ok(events as f64 * 3600.0 / observed_secs)
+}
+
+#[tauri::command]
+async fn set_heart_rate_thresholds(
+    state: tauri::State<'_, AppState>,
+    thresholds: HeartRateThresholds,
+) -> Result<(), String> {
+    let HeartRateThresholds { high, low, hysteresis } = thresholds;
+    if !(high.is_finite() && low.is_finite() && hysteresis.is_finite()) || low <= 0.0 || low >= high {
+        return Err("thresholds must satisfy 0 < low < high".to_string());
+    }
+    if hysteresis < 0.0 || hysteresis * 2.0 >= high - low {
+        return Err("hysteresis must be non-negative and less than half of high - low".to_string());
+    }
+    *lock_or_recover(&state.heart_rate_thresholds) = thresholds;
+    *lock_or_recover(&state.heart_rate_zone) = HeartRateZone::Normal;
+    Ok(())
+}
+
+// Movement intensity (0-1) and how long it must stay above it before a movement_alert
+#[tauri::command]
+async fn set_movement_alert(state: tauri::State<'_, AppState>, config: MovementAlertConfig) -> Result<(), String> {
+    if !(config.threshold > 0.0 && config.threshold <= 1.0) {
+        return Err("threshold must be in (0, 1]".to_string());
+    }
+    if !(config.dwell_secs.is_finite() && config.dwell_secs >= 0.0) {
+        return Err("dwell_secs must be non-negative".to_string());
+    }
+    *lock_or_recover(&state.movement_alert) = config;
+    *lock_or_recover(&state.fidget_episode) = None;
+    println!("🌀 Movement alert: above {:.2} for {:.1}s", config.threshold, config.dwell_secs);
+    Ok(())
+}
+
+// Weight of the newest reading in the smoothed focus score; 1 follows the raw score
+#[tauri::command]
+async fn set_smoothing(state: tauri::State<'_, AppState>, alpha: f64) -> Result<(), String> {
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        return Err("alpha must be greater than 0 and at most 1".to_string());
+    }
+    *lock_or_recover(&state.score_smoothing_alpha) = alpha;
+    Ok(())
+}
+
+// How long a new state must hold before it is committed; applies on the next reading
+#[tauri::command]
+async fn set_stability_window(state: tauri::State<'_, AppState>, secs: f64) -> Result<(), String> {
+    if !(0.0..=MAX_STABILITY_WINDOW_SECS).contains(&secs) {
+        return Err(format!("secs must be between 0 and {}", MAX_STABILITY_WINDOW_SECS));
+    }
+    *lock_or_recover(&state.stability_window) = std::time::Duration::from_secs_f64(secs);
+    println!("⏱️ Stability window: {:.1}s", secs);
+    Ok(())
+}
+
+// Time between Muse polls; applies from the next iteration. Values below the
+// minimum are clamped to it and reported as an error.
+#[tauri::command]
+async fn set_poll_interval(state: tauri::State<'_, AppState>, ms: u64) -> Result<(), String> {
+    let (interval, result) = clamp_poll_interval(ms);
+    *lock_or_recover(&state.poll_interval) = interval;
+    info!("⏱️ Poll interval: {}ms", interval.as_millis());
+    result
+}
+
+fn clamp_poll_interval(ms: u64) -> (std::time::Duration, Result<(), String>) {
+    let clamped = ms.clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS);
+    let result = if clamped == ms {
+        Ok(())
+    } else {
+        Err(format!(
+            "ms must be between {} and {}; using {}ms",
+            MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS, clamped
+        ))
+    };
+    (std::time::Duration::from_millis(clamped), result)
+}
+
+// Mark the latest distraction nudge as wrong, as feedback for auto_tune_thresholds
+#[tauri::command]
+async fn flag_false_positive(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let at = lock_or_recover(&state.focus_transitions)
+        .iter()
+        .rev()
+        .find(|t| t.focus_state == "unfocused")
+        .map(|t| t.at)
+        .ok_or("No distraction nudge to flag")?;
+
+    let mut flagged = lock_or_recover(&state.false_positives);
+    if flagged.contains(&at) {
+        return Err("Latest distraction nudge is already flagged".to_string());
+    }
+    flagged.push(at);
+    println!("🚩 Flagged false positive at {} ({} pending)", at.to_rfc3339(), flagged.len());
+    Ok(())
+}
+
+// Raise the debounce so brief flagged distractions would not have nudged
+#[tauri::command]
+async fn auto_tune_thresholds(state: tauri::State<'_, AppState>) -> Result<ThresholdTuning, String> {
+    // Only episodes that have ended tell us how long the false distraction lasted
+    let (used, episodes): (Vec<_>, Vec<f64>) = {
+        let flagged = lock_or_recover(&state.false_positives);
+        let transitions = lock_or_recover(&state.focus_transitions);
+        flagged
+            .iter()
+            .filter_map(|&flagged_at| {
+                transitions
+                    .iter()
+                    .find(|t| t.at > flagged_at && t.focus_state == "focused")
+                    .map(|t| (flagged_at, (t.at - flagged_at).num_milliseconds() as f64 / 1000.0))
+            })
+            .unzip()
+    };
+
+    if used.len() < MIN_TUNING_FEEDBACK {
+        return Err(format!(
+            "Need at least {} resolved false positives to tune (have {})",
+            MIN_TUNING_FEEDBACK,
+            used.len()
+        ));
+    }
+
+    let median = analytics::median(&episodes).unwrap_or(0.0);
+    let debounce_before = lock_or_recover(&state.stability_window).as_secs_f64();
+    let debounce_after = (debounce_before + median).min(MAX_STABILITY_WINDOW_SECS).max(debounce_before);
+    let adjusted = debounce_after > debounce_before;
+
+    *lock_or_recover(&state.stability_window) = std::time::Duration::from_secs_f64(debounce_after);
+    lock_or_recover(&state.false_positives).retain(|at| !used.contains(at));
+
+    if adjusted {
+        println!("🎛️ Debounce tuned {:.1}s -> {:.1}s from {} false positives",
+                 debounce_before, debounce_after, used.len());
+        broadcast(&state, DuckMessage::new(
+            "thresholds_tuned",
+            format!(
+                "Distraction debounce raised from {:.1}s to {:.1}s based on {} flagged false positives",
+                debounce_before, debounce_after, used.len()
+            ),
+        ));
+    } else {
+        println!("🎛️ Debounce already at its {:.1}s limit", MAX_STABILITY_WINDOW_SECS);
+    }
+
+    Ok(ThresholdTuning {
+        feedback_used: used.len(),
+        debounce_before,
+        debounce_after,
+        adjusted,
+    })
+}
+
+// Retained transitions whose focus_score moved by at least `min_score_delta`
+#[tauri::command]
+async fn get_significant_transitions(
+    state: tauri::State<'_, AppState>,
+    min_score_delta: f64,
+) -> Result<Vec<DuckMessage>, String> {
+    if !min_score_delta.is_finite() || min_score_delta < 0.0 {
+        return Err("min_score_delta must be a non-negative number".to_string());
+    }
+
+    Ok(lock_or_recover(&state.focus_transitions)
+        .iter()
+        .filter(|t| (t.focus_score - t.score_before).abs() >= min_score_delta)
+        .map(|t| t.message.clone())
+        .collect())
+}
+
+// Stop polling after `max` failed discoveries in a row; None retries forever
+#[tauri::command]
+async fn set_max_reconnect_attempts(state: tauri::State<'_, AppState>, max: Option<u32>) -> Result<(), String> {
+    if max == Some(0) {
+        return Err("max must be at least 1".to_string());
+    }
+    *lock_or_recover(&state.max_reconnect_attempts) = max;
+    println!("🔁 Max reconnect attempts: {:?}", max);
+    Ok(())
+}
+
+// Which ports discovery tried, what each returned and which one is in use
+#[tauri::command]
+async fn get_discovery_report(state: tauri::State<'_, AppState>) -> Result<DiscoveryReport, String> {
+    let probes = lock_or_recover(&state.port_probes);
+    let ports = state
+        .muse_ports
+        .iter()
+        .map(|&port| PortDiagnostics {
+            port,
+            last_attempt: probes.get(&port).cloned(),
+        })
+        .collect();
+
+    Ok(DiscoveryReport {
+        active_port: *lock_or_recover(&state.active_muse_port),
+        forced_port: state.forced_muse_port,
+        ports,
+    })
+}
+
+// Re-read duck_config.json and apply it; an invalid file leaves the current settings in place
+#[tauri::command]
+async fn reload_config(state: tauri::State<'_, AppState>) -> Result<config::Config, String> {
+    let Some(path) = &state.config_path else {
+        return Err("No config file location is available".to_string());
+    };
+    let loaded = config::load_config(path).inspect_err(|e| warn!("⚠️ {}; keeping current settings", e))?;
+    apply_config(&state, &loaded);
+    if muse_ports_from_config(&loaded) != *state.muse_ports {
+        warn!("⚠️ muse_ports changes take effect after a restart");
+    }
+    info!("🔧 Reloaded {}", path.display());
+    Ok(loaded)
+}
+
+// Push file tunables into the live state; out-of-range values are clamped or skipped
+fn apply_config(state: &AppState, config: &config::Config) {
+    let (interval, result) = clamp_poll_interval(config.poll_interval_ms);
+    if let Err(e) = result {
+        warn!("⚠️ Config poll_interval_ms: {}", e);
+    }
+    *lock_or_recover(&state.poll_interval) = interval;
+
+    if (0.0..=MAX_STABILITY_WINDOW_SECS).contains(&config.stability_window_secs) {
+        *lock_or_recover(&state.stability_window) = std::time::Duration::from_secs_f64(config.stability_window_secs);
+    } else {
+        warn!(
+            "⚠️ Config stability_window_secs must be between 0 and {}; ignoring {}",
+            MAX_STABILITY_WINDOW_SECS, config.stability_window_secs
+        );
+    }
+
+    if config.disconnect_threshold >= 1 {
+        *lock_or_recover(&state.disconnect_threshold) = config.disconnect_threshold;
+    } else {
+        warn!("⚠️ Config disconnect_threshold must be at least 1; ignoring 0");
+    }
+
+    match config.classification_mode.validate() {
+        Ok(()) => {
+            let mut mode = lock_or_recover(&state.classification_mode);
+            if *mode != config.classification_mode {
+                *mode = config.classification_mode;
+                lock_or_recover(&state.classification_window).clear();
+            }
+        }
+        Err(e) => warn!("⚠️ Config classification_mode: {}; ignoring", e),
+    }
+}
+
+// Ports from the config file, or MUSE_API_PORTS / the defaults when it lists none
+fn muse_ports_from_config(config: &config::Config) -> Vec<u16> {
+    if std::env::var_os(MUSE_API_PORTS_ENV).is_some() {
+        return muse_ports_from_env();
+    }
+    match &config.muse_ports {
+        Some(ports) if ports.iter().any(|&p| p > 0) => {
+            let mut unique: Vec<u16> = Vec::new();
+            for &port in ports.iter().filter(|&&p| p > 0) {
+                if !unique.contains(&port) {
+                    unique.push(port);
+                }
+            }
+            unique
+        }
+        _ => muse_ports_from_env(),
+    }
+}
+
+// Number of failed polls in a row before the headset counts as disconnected
+#[tauri::command]
+async fn set_disconnect_threshold(state: tauri::State<'_, AppState>, n: u32) -> Result<(), String> {
+    if n == 0 {
+        return Err("n must be at least 1".to_string());
+    }
+    *lock_or_recover(&state.disconnect_threshold) = n;
+    println!("🔌 Disconnect threshold: {} consecutive failures", n);
+    Ok(())
+}
+
+// Resume polling after the monitor gave up on the headset
+#[tauri::command]
+async fn reset_connection(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    *lock_or_recover(&state.reconnect_attempts) = 0;
+    let was_abandoned = std::mem::replace(&mut *lock_or_recover(&state.reconnect_gave_up), false);
+    if was_abandoned {
+        println!("🔁 Reconnect requested, resuming Muse polling");
+    }
+    Ok(())
+}
+
+// Set the priority attached to `msg_type` (or `msg_type:focus_state`); None removes it
+#[tauri::command]
+async fn set_message_priority(
+    state: tauri::State<'_, AppState>,
+    msg_type: String,
+    priority: Option<u8>,
+) -> Result<(), String> {
+    if msg_type.trim().is_empty() {
+        return Err("msg_type must not be empty".to_string());
+    }
+    let mut priorities = lock_or_recover(&state.message_priorities);
+    match priority {
+        Some(p) if p > PRIORITY_IMPORTANT => {
+            return Err(format!("priority must be between {} and {}", PRIORITY_INFO, PRIORITY_IMPORTANT));
+        }
+        Some(p) => {
+            priorities.insert(msg_type, p);
+        }
+        None => {
+            priorities.remove(&msg_type);
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_message_priorities(state: tauri::State<'_, AppState>) -> Result<HashMap<String, u8>, String> {
+    Ok(lock_or_recover(&state.message_priorities).clone())
+}
+
+// Applies on the next discovery (i.e. after the current port is lost)
+#[tauri::command]
+async fn set_port_selection(state: tauri::State<'_, AppState>, policy: PortSelection) -> Result<(), String> {
+    *lock_or_recover(&state.port_selection) = policy;
+    println!("🎯 Port selection policy: {:?}", policy);
+    Ok(())
+}
+
+// Throttle repeat nudges independently for focused, unfocused and drowsy
+#[tauri::command]
+async fn set_state_cooldowns(state: tauri::State<'_, AppState>, cooldowns: StateCooldowns) -> Result<(), String> {
+    for secs in [cooldowns.focused, cooldowns.unfocused, cooldowns.drowsy] {
+        if !secs.is_finite() || secs < 0.0 {
+            return Err("cooldowns must be non-negative numbers of seconds".to_string());
+        }
+    }
+    *lock_or_recover(&state.state_cooldowns) = cooldowns;
+    println!("⏲️ State cooldowns: {:?}", cooldowns);
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_state_cooldowns(state: tauri::State<'_, AppState>) -> Result<StateCooldowns, String> {
+    Ok(*lock_or_recover(&state.state_cooldowns))
+}
+
+// POST a control request (e.g. recalibrate) to the bridge; only /api/ paths are allowed
+#[tauri::command]
+async fn send_python_command(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    if !path.starts_with("/api/") || path.contains("..") || path.chars().any(char::is_whitespace) {
+        return Err("path must be an /api/ route on the bridge".to_string());
+    }
+
+    let active_port = *lock_or_recover(&state.active_muse_port);
+    let port = match active_port {
+        Some(port) => port,
+        None => {
+            let policy = *lock_or_recover(&state.port_selection);
+            match state.forced_muse_port {
+                Some(port) => port,
+                None => discover_muse_port(&state.http_client, &state.muse_ports, None, policy, &state.port_probes)
+                    .await
+                    .ok_or("Muse API not found on any port")?,
+            }
+        }
+    };
+
+    let url = format!("http://localhost:{}{}", port, path);
+    println!("📨 Sending bridge command to {}", url);
+    let response = state
+        .http_client
+        .post(&url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Bridge request failed: {}", e))?;
+
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Bridge returned {}: {}", status, text));
+    }
+    if text.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_str(&text).map_err(|e| format!("Bridge returned invalid JSON: {}", e))
+}
+
+// Select the bridge's JSON field naming; `auto` re-detects on the next response
+#[tauri::command]
+async fn set_muse_schema(state: tauri::State<'_, AppState>, schema: MuseSchema) -> Result<(), String> {
+    *lock_or_recover(&state.muse_schema) = schema;
+    *lock_or_recover(&state.detected_schema) = None;
+    println!("🧩 Muse schema set to {:?}", schema);
+    Ok(())
+}
+
+// Frontend signal that a fullscreen app is (or is no longer) in the foreground
+#[tauri::command]
+async fn set_fullscreen_active(state: tauri::State<'_, AppState>, active: bool) -> Result<(), String> {
+    *lock_or_recover(&state.fullscreen_active) = active;
+    if !active {
+        *lock_or_recover(&state.suppression_notified) = false;
+    }
+    println!("🖥️ Fullscreen active: {}", active);
+    Ok(())
+}
+
+// HTTP endpoint to receive messages from Python backend
+async fn receive_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(message): Json<DuckMessage>,
+) -> impl IntoResponse {
+    if !is_authorized(state.api_token.as_deref().map(String::as_str), &headers) {
+        warn!("🔒 Rejected /api/message without a valid token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": "Missing or invalid bearer token"
+            })),
+        );
+    }
+
+    println!("📨 Received from Python: {}", message.message);
+
+    record_message(&state);
+
+    // Emit to Tauri frontend and WebSocket clients (browser extension)
+    broadcast(&state, message);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "success",
+            "broadcasted": true
+        })),
+    )
+}
+
+// Whether the request carries `Authorization: Bearer <expected>`; always true without a token
+fn is_authorized(expected: Option<&str>, headers: &HeaderMap) -> bool {
+    let Some(expected) = expected else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, expected))
+}
+
+// Exact comparison that doesn't stop at the first differing byte, so response
+// timing doesn't reveal how much of a guessed token was right
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// Token for /api/message and /api/drain from DUCK_API_TOKEN; warns when they are left open
+fn api_token_from_env() -> Option<String> {
+    match std::env::var(API_TOKEN_ENV) {
+        Ok(token) if !token.trim().is_empty() => {
+            info!("🔒 /api/message and /api/drain require a bearer token");
+            Some(token.trim().to_string())
+        }
+        _ => {
+            warn!("⚠️ {} not set - any local process can post to /api/message and /api/drain", API_TOKEN_ENV);
+            None
+        }
+    }
+}
+
+// HTTP endpoint to deliver a message to one WebSocket client instead of broadcasting
+async fn receive_client_message(
+    State(state): State<AppState>,
+    Path(client_id): Path<String>,
+    headers: HeaderMap,
+    Json(message): Json<DuckMessage>,
+) -> impl IntoResponse {
+    if !is_authorized(state.api_token.as_deref().map(String::as_str), &headers) {
+        warn!("🔒 Rejected /api/message/{} without a valid token", client_id);
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": "Missing or invalid bearer token"
+            })),
+        );
+    }
+
+    let Some(client) = lock_or_recover(&state.ws_clients).get(&client_id).cloned() else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("No WebSocket client '{}'", client_id)
+            })),
+        );
+    };
+
+    info!("📨 Received for client {}: {}", client_id, message.message);
+    if client.try_send(prioritize_ordered(&state, message)).is_err() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("Client '{}' is not accepting messages", client_id)
+            })),
+        );
+    }
+    record_message(&state);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "success",
+            "client_id": client_id
+        })),
+    )
+}
+
+// HTTP endpoint to receive video from Python backend
+async fn receive_video(
+    State(state): State<AppState>,
+    Json(video): Json<VideoMessage>,
+) -> impl IntoResponse {
+    println!("🎬 Received video: {}", video.video_url);
+
+    // Create message to send to extension
+    let video_message = DuckMessage {
+        timestamp: video.timestamp,
+        ..DuckMessage::new("video", video.video_url.clone())
+    };
+
+    // Emit to Tauri frontend (Activity Log) and WebSocket clients (browser extension)
+    broadcast(&state, video_message);
+
+    Json(serde_json::json!({
+        "status": "success",
+        "video_url": video.video_url
+    }))
+}
+
+// HTTP endpoint to receive context annotations from external apps
+async fn receive_annotation(
+    State(state): State<AppState>,
+    Json(request): Json<AnnotationRequest>,
+) -> impl IntoResponse {
+    let at = match request.timestamp.as_deref() {
+        Some(ts) => match chrono::DateTime::parse_from_rfc3339(ts) {
+            Ok(parsed) => parsed.with_timezone(&chrono::Utc),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "status": "error",
+                        "message": format!("Invalid timestamp: {}", e)
+                    })),
+                );
+            }
+        },
+        None => chrono::Utc::now(),
+    };
+
+    println!("📝 Received annotation: {} ({})", request.kind, request.context);
+
+    // Attach the focus state that was in effect when the annotation was made
+    let transition = lock_or_recover(&state.focus_transitions)
+        .iter()
+        .rev()
+        .find(|t| t.at <= at)
+        .cloned();
+
+    let annotation = Annotation {
+        kind: request.kind,
+        context: request.context,
+        timestamp: at.to_rfc3339(),
+        focus_state: transition.as_ref().map(|t| t.focus_state.clone()),
+        focus_score: transition.map(|t| t.focus_score),
+    };
+
+    {
+        let mut annotations = lock_or_recover(&state.annotations);
+        if annotations.len() >= ANNOTATION_BUFFER_SIZE {
+            annotations.pop_front();
+        }
+        annotations.push_back(annotation.clone());
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "success",
+            "annotation": annotation
+        })),
+    )
+}
+
+// WebSocket handler for browser extension
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if state.draining.load(Ordering::SeqCst) {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server is draining").into_response();
+    }
+    if let Some(v) = params.v.filter(|&v| v == 0 || v > WS_ENVELOPE_VERSION) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Unsupported message format v={} (supported: 1)", v),
+        )
+            .into_response();
+    }
+    ws.on_upgrade(|socket| handle_websocket(socket, state, params))
+}
+
+// HTTP endpoint to stop accepting WebSocket clients ahead of a restart
+async fn drain_server(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if !is_authorized(state.api_token.as_deref().map(String::as_str), &headers) {
+        warn!("🔒 Rejected /api/drain without a valid token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": "Missing or invalid bearer token"
+            })),
+        );
+    }
+
+    let already_draining = state.draining.swap(true, Ordering::SeqCst);
+    if !already_draining {
+        println!("🚰 Draining: refusing new WebSocket connections");
+        broadcast(&state, DuckMessage::new(
+            "server_shutting_down",
+            "Server shutting down - please reconnect shortly",
+        ));
+    }
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "draining",
+            "already_draining": already_draining,
+            "clients": ws_client_count(&state)
+        })),
+    )
+}
+
+// HTTP endpoint (DELETE /api/drain) to accept WebSocket clients again when a
+// drain was called off
+async fn undrain_server(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if !is_authorized(state.api_token.as_deref().map(String::as_str), &headers) {
+        warn!("🔒 Rejected /api/drain without a valid token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": "Missing or invalid bearer token"
+            })),
+        );
+    }
+
+    let was_draining = state.draining.swap(false, Ordering::SeqCst);
+    if was_draining {
+        info!("🚰 Drain cancelled: accepting WebSocket connections again");
+    }
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "accepting",
+            "was_draining": was_draining
+        })),
+    )
+}
+
+// Serialize a message into a WebSocket frame, logging (not panicking) on failure
+fn encode_message(msg: &DuckMessage, version: Option<u32>, format: WsFormat) -> Option<Message> {
+    let encoded = match version {
+        Some(v) => encode_frame(
+            &WsEnvelope {
+                v,
+                kind: &msg.msg_type,
+                data: msg,
+            },
+            format,
+        ),
+        None => encode_frame(msg, format),
+    };
+    match encoded {
+        Ok(frame) => Some(frame),
+        Err(e) => {
+            eprintln!("⚠️ Skipping unserializable {} message: {}", msg.msg_type, e);
+            None
+        }
+    }
+}
+
+fn encode_frame<T: Serialize>(value: &T, format: WsFormat) -> Result<Message, String> {
+    match format {
+        WsFormat::Json => serde_json::to_string(value).map(Message::Text).map_err(|e| e.to_string()),
+        // Named (map) encoding keeps field names, so frames decode back into a DuckMessage
+        WsFormat::Msgpack => rmp_serde::to_vec_named(value).map(Message::Binary).map_err(|e| e.to_string()),
+    }
+}
+
+// Current EEG connection status, as sent to newly connected clients
+fn connection_status_message(state: &AppState) -> DuckMessage {
+    let is_connected = *lock_or_recover(&state.muse_connected);
+    let message = if is_connected {
+        "EEG Connected"
+    } else {
+        "EEG Disconnected - Please connect your Muse headset"
+    };
+    DuckMessage::new("connection_status", message)
+}
+
+// Liveness signal carrying the current connection and focus state
+fn heartbeat_message(state: &AppState) -> DuckMessage {
+    let is_connected = *lock_or_recover(&state.muse_connected);
+    DuckMessage {
+        focus_state: lock_or_recover(&state.last_focus_state).clone(),
+        metrics: latest_message_metrics(state),
+        ..DuckMessage::new("heartbeat", if is_connected { "EEG Connected" } else { "EEG Disconnected" })
+    }
+}
+
+// Current focus state and latest reading, so a client connecting mid-session
+// can render right away; None before any data has been collected
+fn snapshot_message(state: &AppState) -> Option<DuckMessage> {
+    let focus_state = lock_or_recover(&state.last_focus_state).clone();
+    let metrics = latest_message_metrics(state);
+    if focus_state.is_none() && metrics.is_none() {
+        return None;
+    }
+    Some(DuckMessage {
+        focus_state,
+        metrics,
+        ..DuckMessage::new("snapshot", "Current state")
+    })
+}
+
+fn latest_message_metrics(state: &AppState) -> Option<MessageMetrics> {
+    lock_or_recover(&state.latest_metrics).as_ref().map(|m| MessageMetrics {
+        attention: m.attention.clone(),
+        focus_score: m.focus_score,
+        brain_state: m.brain_state.clone(),
+    })
+}
+
+// Broadcast a heartbeat every heartbeat_interval_secs until shutdown. Not
+// counted in messages_received, which tracks messages from the backend.
+async fn run_heartbeat(state: AppState) {
+    loop {
+        let secs = *lock_or_recover(&state.heartbeat_interval_secs);
+        // Recheck the setting periodically while disabled
+        let wait = std::time::Duration::from_secs(if secs == 0 { 1 } else { secs });
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown_signal(state.clone()) => return,
+        }
+        if secs != 0 && *lock_or_recover(&state.heartbeat_interval_secs) != 0 {
+            broadcast(&state, heartbeat_message(&state));
+        }
+    }
+}
+
+// Sent to a client that fell behind the broadcast channel instead of dropping it:
+// a resync notice followed by the current connection status it may have missed
+fn resync_messages(state: &AppState, skipped: u64) -> Vec<DuckMessage> {
+    warn!("⚠️ WebSocket client lagged, skipped {} messages", skipped);
+    let notice = DuckMessage::new("resync", format!("Missed {} messages - resyncing", skipped));
+    let status = connection_status_message(state);
+    let _order = lock_or_recover(&state.broadcast_order);
+    vec![prioritize(state, notice), prioritize(state, status)]
+}
+
+// Ping the client, or report it dead (false) if it missed the previous pong deadline
+async fn send_keepalive(
+    sender: &mut SplitSink<WebSocket, Message>,
+    last_pong: &Mutex<std::time::Instant>,
+) -> bool {
+    let silent_for = lock_or_recover(last_pong).elapsed();
+    if silent_for.as_secs() >= WS_PING_INTERVAL_SECS + WS_PONG_TIMEOUT_SECS {
+        println!("🔌 WebSocket client missed keepalive ({}s without pong), disconnecting", silent_for.as_secs());
+        return false;
+    }
+    sender.send(Message::Ping(Vec::new())).await.is_ok()
+}
+
+async fn handle_websocket(socket: WebSocket, state: AppState, params: WsParams) {
+    let (mut sender, mut receiver) = socket.split();
+
+    println!("🔌 WebSocket client connected");
+
+    // Send welcome message
+    let welcome = DuckMessage::new("connection", "Connected to Duck Controller!");
+
+    // Followed by the current EEG connection status and, if any have been
+    // collected, the current focus state and metrics
+    let greeting = [Some(welcome), Some(connection_status_message(&state)), snapshot_message(&state)];
+
+    // Number the greeting and subscribe under the broadcast lock, so every
+    // broadcast this client receives has a higher seq than the greeting
+    let (greeting, mut rx) = {
+        let _order = lock_or_recover(&state.broadcast_order);
+        let greeting: Vec<DuckMessage> = greeting.into_iter().flatten().map(|msg| prioritize(&state, msg)).collect();
+        (greeting, state.ws_tx.subscribe())
+    };
+    for msg in greeting {
+        if let Some(frame) = encode_message(&msg, params.v, params.format) {
+            if sender.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    // Register for targeted messages; a reconnect under the same id takes over
+    let client_id = params
+        .client_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let (direct_tx, mut direct_rx) = mpsc::channel::<DuckMessage>(WS_DIRECT_QUEUE_SIZE);
+    if lock_or_recover(&state.ws_clients).insert(client_id.clone(), direct_tx.clone()).is_some() {
+        info!("🔌 Client '{}' reconnected, replacing its previous connection", client_id);
+    }
+    debug!("🔌 WebSocket client id: {}", client_id);
+
+    let version = params.v;
+    let format = params.format;
+    let buffer_ms = params.buffer_ms.filter(|&ms| ms > 0).map(|ms| ms.min(MAX_WS_BUFFER_MS));
+    if let Some(ms) = buffer_ms {
+        println!("🔌 Client requested {}ms message buffering", ms);
+    }
+
+    // Last time the client answered a ping; shared with the receive task
+    let last_pong = Arc::new(Mutex::new(std::time::Instant::now()));
+    let send_last_pong = last_pong.clone();
+    let send_state = state.clone();
+
+    // Spawn task to forward broadcast messages to this WebSocket
+    let mut send_task = tokio::spawn(async move {
+        let ping_period = tokio::time::Duration::from_secs(WS_PING_INTERVAL_SECS);
+        let mut keepalive = tokio::time::interval_at(tokio::time::Instant::now() + ping_period, ping_period);
+
+        let Some(ms) = buffer_ms else {
+            loop {
+                // Broadcasts first: a direct message is numbered after anything
+                // already in the channel
+                tokio::select! {
+                    biased;
+                    received = rx.recv() => {
+                        let messages = match received {
+                            Ok(msg) => vec![msg],
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => resync_messages(&send_state, skipped),
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        };
+                        for msg in messages {
+                            let Some(frame) = encode_message(&msg, version, format) else {
+                                continue;
+                            };
+                            if sender.send(frame).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Some(msg) = direct_rx.recv() => {
+                        let Some(frame) = encode_message(&msg, version, format) else {
+                            continue;
+                        };
+                        if sender.send(frame).await.is_err() {
+                            break;
+                        }
+                    }
+                    _ = keepalive.tick() => {
+                        if !send_keepalive(&mut sender, &send_last_pong).await {
+                            break;
+                        }
+                    }
+                }
+            }
+            return;
+        };
+
+        // Buffered mode: keep only the newest message of each type between flushes
+        let mut pending: Vec<DuckMessage> = Vec::new();
+        let mut flush = tokio::time::interval(tokio::time::Duration::from_millis(ms));
+        loop {
+            tokio::select! {
+                received = rx.recv() => {
+                    let messages = match received {
+                        Ok(msg) => vec![msg],
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => resync_messages(&send_state, skipped),
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    for msg in messages {
+                        match pending.iter_mut().find(|m| m.msg_type == msg.msg_type) {
+                            Some(existing) => *existing = msg,
+                            None => pending.push(msg),
+                        }
+                    }
+                }
+                Some(msg) = direct_rx.recv() => {
+                    match pending.iter_mut().find(|m| m.msg_type == msg.msg_type) {
+                        Some(existing) => *existing = msg,
+                        None => pending.push(msg),
+                    }
+                }
+                _ = flush.tick() => {
+                    // A replaced message keeps its type's slot, so restore seq order
+                    pending.sort_by_key(|msg| msg.seq);
+                    for msg in pending.drain(..) {
+                        let Some(frame) = encode_message(&msg, version, format) else {
+                            continue;
+                        };
+                        if sender.send(frame).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                _ = keepalive.tick() => {
+                    if !send_keepalive(&mut sender, &send_last_pong).await {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    // Handle incoming messages from WebSocket (if any)
+    let mut recv_task = tokio::spawn(async move {
+        while let Some(Ok(msg)) = receiver.next().await {
+            match msg {
+                Message::Text(text) => println!("📩 Received from extension: {}", text),
+                Message::Pong(_) => *lock_or_recover(&last_pong) = std::time::Instant::now(),
+                _ => {}
+            }
+        }
+    });
+
+    // Wait for either task to finish (connection closed) or for shutdown
+    tokio::select! {
+        _ = (&mut send_task) => recv_task.abort(),
+        _ = (&mut recv_task) => send_task.abort(),
+        _ = shutdown_signal(state.clone()) => {
+            send_task.abort();
+            recv_task.abort();
+        }
+    }
+
+    // Leave the entry alone if a newer connection has taken over this id
+    let mut clients = lock_or_recover(&state.ws_clients);
+    if clients.get(&client_id).is_some_and(|tx| tx.same_channel(&direct_tx)) {
+        clients.remove(&client_id);
+    }
+    println!("🔌 WebSocket client disconnected");
+}
+
+// Ask the server, monitor and WebSocket tasks to stop
+fn request_shutdown(state: &AppState) {
+    if !state.shutdown.send_replace(true) {
+        println!("🛑 Shutting down HTTP server and monitor...");
+    }
+}
+
+// Stop the server and monitor and kill the Python backend. Safe to call more than
+// once: window close and a termination signal can both end up here.
+fn shutdown_app(state: &AppState) {
+    request_shutdown(state);
+    let python_process = lock_or_recover(&state.python_process).take();
+    if let Some(mut child) = python_process {
+        println!("🛑 Shutting down Python backend...");
+        let _ = child.kill();
+        let _ = child.wait();
+        println!("✅ Python backend stopped");
+    }
+}
+
+// Resolves on Ctrl+C, or SIGTERM on Unix; never resolves if neither can be watched
+async fn termination_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
+            }
+            Err(e) => eprintln!("⚠️ Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    ctrl_c().await;
+}
+
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        eprintln!("⚠️ Failed to listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+// Resolves once shutdown has been requested
+async fn shutdown_signal(state: AppState) {
+    let mut rx = state.shutdown.subscribe();
+    let _ = rx.wait_for(|stopping| *stopping).await;
+}
+
+// Health check endpoint
+async fn health_check() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "running",
+        "service": "Duck Controller - Tauri Backend"
+    }))
+}
+
+// Latest cached Muse metrics, so clients don't need to find the Python port
+async fn latest_metrics_endpoint(State(state): State<AppState>) -> impl IntoResponse {
+    match lock_or_recover(&state.latest_metrics).clone() {
+        Some(metrics) => (StatusCode::OK, Json(serde_json::json!(metrics))),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({
+                "status": "unavailable",
+                "error": "No Muse metrics available yet"
+            })),
+        ),
+    }
+}
+
+// Same status the frontend gets from get_service_status, for the extension and monitoring
+async fn status_endpoint(State(state): State<AppState>) -> Json<ServiceStatus> {
+    Json(service_status(&state))
+}
+
+// Prometheus text exposition for scraping; duck_focus_score is omitted until the first reading
+async fn prometheus_metrics_endpoint(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = String::new();
+    write_prometheus_metric(
+        &mut body,
+        "duck_messages_total",
+        "counter",
+        "Messages received from the backend",
+        *lock_or_recover(&state.message_count) as f64,
+    );
+    write_prometheus_metric(
+        &mut body,
+        "duck_muse_connected",
+        "gauge",
+        "1 while the Muse headset is connected",
+        if *lock_or_recover(&state.muse_connected) { 1.0 } else { 0.0 },
+    );
+    write_prometheus_metric(
+        &mut body,
+        "duck_consecutive_failures",
+        "gauge",
+        "Failed Muse polls in a row",
+        *lock_or_recover(&state.consecutive_failures) as f64,
+    );
+    if let Some(metrics) = lock_or_recover(&state.latest_metrics).as_ref() {
+        write_prometheus_metric(
+            &mut body,
+            "duck_focus_score",
+            "gauge",
+            "Latest focus score (0-1)",
+            metrics.focus_score,
+        );
+    }
+    write_prometheus_metric(
+        &mut body,
+        "duck_ws_clients",
+        "gauge",
+        "Connected WebSocket clients",
+        ws_client_count(&state) as f64,
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+fn write_prometheus_metric(out: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+}
+
+// HTTP endpoint returning the newest `limit` buffered samples, oldest first
+async fn metrics_history_endpoint(
+    Query(params): Query<HistoryParams>,
+    State(state): State<AppState>,
+) -> Json<Vec<MetricsSample>> {
+    let history = lock_or_recover(&state.metrics_history);
+    let limit = params.limit.unwrap_or(METRICS_HISTORY_SIZE).min(history.len());
+    Json(history.iter().skip(history.len() - limit).cloned().collect())
+}
+
+fn default_message_priorities() -> HashMap<String, u8> {
+    [
+        ("focus_state_change:unfocused", PRIORITY_IMPORTANT),
+        ("focus_state_change", PRIORITY_NORMAL),
+        ("composite_alert", PRIORITY_IMPORTANT),
+        ("look_away", PRIORITY_NORMAL),
+        ("connection_abandoned", PRIORITY_IMPORTANT),
+        ("server_shutting_down", PRIORITY_IMPORTANT),
+        ("score_alert", PRIORITY_IMPORTANT),
+        ("connection_status", PRIORITY_NORMAL),
+        ("stale_metrics", PRIORITY_NORMAL),
+        ("heart_rate_alert", PRIORITY_NORMAL),
+        ("video", PRIORITY_NORMAL),
+        ("connection", PRIORITY_INFO),
+        ("connection_display", PRIORITY_INFO),
+        ("nudges_suppressed", PRIORITY_INFO),
+        ("thresholds_tuned", PRIORITY_INFO),
+        ("snapshot", PRIORITY_INFO),
+        ("backend_restart", PRIORITY_NORMAL),
+        ("backend_fatal", PRIORITY_IMPORTANT),
+        ("backend_log", PRIORITY_INFO),
+        ("resync", PRIORITY_NORMAL),
+        ("heartbeat", PRIORITY_INFO),
+    ]
+    .into_iter()
+    .map(|(msg_type, priority)| (msg_type.to_string(), priority))
+    .collect()
+}
+
+// Ports to probe from MUSE_API_PORTS (e.g. "7000,7001"), falling back to the defaults
+fn muse_ports_from_env() -> Vec<u16> {
+    let Ok(value) = std::env::var(MUSE_API_PORTS_ENV) else {
+        return DEFAULT_MUSE_API_PORTS.to_vec();
+    };
+
+    let mut ports = Vec::new();
+    for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.parse::<u16>() {
+            Ok(port) if port > 0 => {
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+            _ => warn!("⚠️ Ignoring invalid port '{}' in {}", part, MUSE_API_PORTS_ENV),
+        }
+    }
+
+    if ports.is_empty() {
+        warn!("⚠️ No valid ports in {}={:?}, using defaults", MUSE_API_PORTS_ENV, value);
+        return DEFAULT_MUSE_API_PORTS.to_vec();
+    }
+    info!("🔧 Muse API ports: {:?}", ports);
+    ports
+}
+
+// Single port from MUSE_API_PORT that is used without discovery
+fn forced_muse_port_from_env() -> Option<u16> {
+    let value = std::env::var(MUSE_API_PORT_ENV).ok()?;
+    match value.trim().parse::<u16>() {
+        Ok(port) if port > 0 => {
+            info!("🔧 Using Muse API port {} (discovery disabled)", port);
+            Some(port)
+        }
+        _ => {
+            warn!("⚠️ Ignoring invalid {}={:?}, discovering ports instead", MUSE_API_PORT_ENV, value);
+            None
+        }
+    }
+}
+
+// Discover which port the Muse API is running on, trying `preferred` first.
+// With any policy other than `first`, every port is probed and one is picked
+// among those that responded. Each probe's outcome is recorded in `probes`.
+async fn discover_muse_port(
+    client: &reqwest::Client,
+    ports: &[u16],
+    preferred: Option<u16>,
+    policy: PortSelection,
+    probes: &Mutex<HashMap<u16, PortProbe>>,
+) -> Option<u16> {
+    let candidates = preferred
+        .into_iter()
+        .chain(ports.iter().copied().filter(|&p| Some(p) != preferred));
+    let record = |port: u16, result: PortProbeResult| {
+        lock_or_recover(probes).insert(port, PortProbe {
+            result,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+    };
+
+    let mut responders: Vec<(u16, Option<f64>)> = Vec::new();
+    for port in candidates {
+        let url = format!("http://localhost:{}/api/metrics", port);
+        match client.get(&url).send().await {
+            Ok(response) => {
+                if response.status().is_success() {
+                    debug!("✅ Found Muse API on port {}", port);
+                    record(port, PortProbeResult::Connected);
+                    if policy == PortSelection::First {
+                        return Some(port);
+                    }
+                    let captured_at = if policy == PortSelection::MostRecentData {
+                        response
+                            .json::<serde_json::Value>()
+                            .await
+                            .ok()
+                            .and_then(|body| MuseSchema::detect(&body))
+                            .and_then(|(_, metrics)| metrics.captured_at)
+                    } else {
+                        None
+                    };
+                    responders.push((port, captured_at));
+                } else {
+                    debug!("⚠️ Port {} responded with status: {}", port, response.status());
+                    record(port, PortProbeResult::HttpStatus(response.status().as_u16()));
+                }
+            }
+            Err(e) => {
+                debug!("❌ Port {} error: {}", port, e);
+                record(port, PortProbeResult::Error(e.to_string()));
+            }
+        }
+    }
+
+    let selected = select_port(policy, &responders);
+    match selected {
+        Some(port) => info!(
+            "🎯 Selected port {} ({:?}, {} responding)",
+            port,
+            policy,
+            responders.len()
+        ),
+        None => warn!("❌ No Muse API found on any port"),
+    }
+    selected
+}
+
+// Pick among responding ports; `most_recent_data` falls back to the first
+// responder when no bridge reports a timestamp
+fn select_port(policy: PortSelection, responders: &[(u16, Option<f64>)]) -> Option<u16> {
+    let ports = responders.iter().map(|(port, _)| *port);
+    match policy {
+        PortSelection::First => ports.clone().next(),
+        PortSelection::LowestPort => ports.clone().min(),
+        PortSelection::HighestPort => ports.clone().max(),
+        PortSelection::MostRecentData => responders
+            .iter()
+            .filter_map(|(port, captured_at)| captured_at.map(|t| (*port, t)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(port, _)| port)
+            .or_else(|| ports.clone().next()),
+    }
+}
+
+// Most frequent label in the classification window with its vote count. A tie
+// goes to the committed state, then to the tied label voted most recently.
+fn leading_label(window: &VecDeque<String>, committed: Option<&str>) -> Option<(String, usize)> {
+    let mut votes: HashMap<&str, usize> = HashMap::new();
+    for label in window {
+        *votes.entry(label.as_str()).or_insert(0) += 1;
+    }
+    let top = votes.values().copied().max()?;
+    let leads = |label: &&str| votes.get(label) == Some(&top);
+    committed
+        .filter(leads)
+        .or_else(|| window.iter().rev().map(String::as_str).find(leads))
+        .map(|label| (label.to_string(), top))
+}
+
+// Background task to monitor Muse metrics and send focus state changes
+async fn monitor_muse_metrics(state: AppState) {
+    let client = state.http_client.clone();
+    let mut last_connection_message_sent = false;
+    let mut muse_port: Option<u16> = None;
+    let mut iteration_start: Option<std::time::Instant> = None;
+
+    loop {
+        // Time the previous iteration's work (excluding the sleep)
+        if let Some(start) = iteration_start {
+            record_loop_iteration(&state, start.elapsed());
+        }
+        record_connection_uptime(&state);
+        *lock_or_recover(&state.active_muse_port) = muse_port;
+
+        let poll_interval = *lock_or_recover(&state.poll_interval);
+        tokio::time::sleep(poll_interval).await;
+        iteration_start = Some(std::time::Instant::now());
+        if *state.shutdown.borrow() {
+            info!("🛑 Muse monitor stopped");
+            break;
+        }
+        flush_pending_nudge(&state);
+        flush_connection_status(&state);
+        retry_failed_emits(&state);
+        update_display_connection(&state);
+
+        // Stay idle after giving up until reset_connection
+        if *lock_or_recover(&state.reconnect_gave_up) {
+            continue;
+        }
+
+        // A port forced via MUSE_API_PORT skips discovery entirely
+        if muse_port.is_none() {
+            if let Some(port) = state.forced_muse_port {
+                muse_port = Some(port);
+                *lock_or_recover(&state.active_muse_port) = Some(port);
+            }
+        }
+
+        // Discover port if not found
+        if muse_port.is_none() {
+            let preferred = if state.remember_muse_port {
+                *lock_or_recover(&state.last_muse_port)
+            } else {
+                None
+            };
+            let policy = *lock_or_recover(&state.port_selection);
+            muse_port = discover_muse_port(&client, &state.muse_ports, preferred, policy, &state.port_probes).await;
+            if let Some(port) = muse_port {
+                *lock_or_recover(&state.active_muse_port) = Some(port);
+                remember_muse_port(&state, port);
+            } else {
+                handle_muse_failure(&state, &mut last_connection_message_sent, "API not found on any port").await;
+                record_reconnect_failure(&state);
+                continue;
+            }
+        }
+
+        // Apply injected faults before the real fetch
+        let fault = lock_or_recover(&state.fault_config).clone();
+        if fault.extra_latency_ms > 0 {
+            tokio::time::sleep(tokio::time::Duration::from_millis(fault.extra_latency_ms)).await;
+        }
+        if fault.force_disconnect {
+            warn!("💉 Injected disconnect");
+            muse_port = None;
+            handle_muse_failure(&state, &mut last_connection_message_sent, "Injected disconnect").await;
+            continue;
+        }
+        if fault.drop_rate > 0.0 && rand::random::<f64>() < fault.drop_rate {
+            warn!("💉 Injected dropped fetch");
+            handle_muse_failure(&state, &mut last_connection_message_sent, "Injected dropped fetch").await;
+            continue;
+        }
+
+        // Fetch metrics from Muse backend
+        let url = format!("http://localhost:{}/api/metrics", muse_port.unwrap());
+        match client.get(&url).send().await {
+            Ok(response) => {
+                // Check if response is successful (not 404)
+                if response.status().is_success() {
+                    let body = match response.text().await {
+                        Ok(body) => body,
+                        Err(e) => {
+                            // The connection dropped mid-body
+                            warn!("⚠️ Failed to read Muse API response: {}", e);
+                            handle_muse_failure(&state, &mut last_connection_message_sent, "Connection error").await;
+                            continue;
+                        }
+                    };
+                    let parsed = serde_json::from_str::<serde_json::Value>(&body)
+                        .ok()
+                        .and_then(|body| parse_metrics(&state, &body));
+                    if let Some(mut metrics) = parsed {
+                        let adjustments = {
+                            let previous = lock_or_recover(&state.latest_metrics);
+                            metrics.validate_and_clamp(previous.as_ref())
+                        };
+                        if !adjustments.is_empty() {
+                            warn!("🩹 Corrected Muse metrics: {}", adjustments.join("; "));
+                        }
+                        *lock_or_recover(&state.latest_metrics) = Some(metrics.clone());
+
+                        // Mark as connected
+                        {
+                            let mut connected = lock_or_recover(&state.muse_connected);
+                            let mut failures = lock_or_recover(&state.consecutive_failures);
+
+                            if !*connected {
+                                info!("✅ Muse EEG connected!");
+                                if let Some(sink) = &state.syslog {
+                                    sink.send(syslog::Severity::Notice, "CONNECTION", "muse connected");
+                                }
+                                *connected = true;
+                                *failures = 0;
+                                *lock_or_recover(&state.reconnect_attempts) = 0;
+                                last_connection_message_sent = false;
+
+                                // Send connection status message
+                                let conn_msg = DuckMessage::new("connection_status", "EEG Connected");
+
+                                send_connection_status(&state, true, conn_msg);
+                            }
+                        }
+
+                        // Stale readings (by the bridge's own timestamp) don't drive the duck
+                        if metrics_are_stale(&state, &metrics) {
+                            continue;
+                        }
+
+                        // Record the sample in the history buffer
+                        let sample_secs = {
+                            let now = chrono::Utc::now();
+                            let mut history = lock_or_recover(&state.metrics_history);
+                            let sample_secs = history
+                                .back()
+                                .map(|prev| (now - prev.at).num_milliseconds() as f64 / 1000.0)
+                                .unwrap_or(0.0)
+                                .clamp(0.0, MAX_SAMPLE_GAP_SECS);
+                            if history.len() >= METRICS_HISTORY_SIZE {
+                                history.pop_front();
+                            }
+                            history.push_back(MetricsSample {
+                                timestamp: now.to_rfc3339(),
+                                at: now,
+                                metrics: metrics.clone(),
+                            });
+                            sample_secs
+                        };
+                        persist_sample(&state, &metrics, sample_secs);
+                        if let Some(active) = lock_or_recover(&state.recording).as_mut() {
+                            if active.include_metrics {
+                                active.write_entry("metrics", &metrics);
+                            }
+                        }
+
+                        // Real readings pause while a simulation drives the pipeline
+                        if *lock_or_recover(&state.simulation_active) {
+                            continue;
+                        }
+                        process_metrics(&state, &metrics, std::time::Instant::now());
+                    } else {
+                        // The bridge is up but sent a partial or malformed frame;
+                        // that is not a disconnect, so it doesn't count as a failure
+                        let parse_errors = {
+                            let mut count = lock_or_recover(&state.parse_error_count);
+                            *count += 1;
+                            *count
+                        };
+                        debug!("⚠️ Unparseable Muse API response (#{}): {}", parse_errors, body);
+                    }
+                } else {
+                    // Non-200 status - port might have changed
+                    warn!("⚠️ Lost connection, rediscovering port...");
+                    muse_port = None;
+                    handle_muse_failure(&state, &mut last_connection_message_sent, "Connection lost").await;
+                }
+            }
+            Err(_) => {
+                // Connection error - port might have changed
+                warn!("⚠️ Connection error, rediscovering port...");
+                muse_port = None;
+                handle_muse_failure(&state, &mut last_connection_message_sent, "Connection error").await;
+            }
+        }
+    }
+}
+
+// Move the user-facing connection state toward the raw one, but only after the
+// current state has been shown for the minimum display time
+fn update_display_connection(state: &AppState) {
+    let raw = if *lock_or_recover(&state.muse_connected) {
+        "connected"
+    } else if *lock_or_recover(&state.reconnect_gave_up) {
+        "disconnected"
+    } else {
+        "connecting"
+    };
+    let min_secs = *lock_or_recover(&state.connection_display_min_secs);
+
+    {
+        let mut display = lock_or_recover(&state.display_connection);
+        if display.state == raw || display.since.elapsed().as_secs_f64() < min_secs {
+            return;
+        }
+        *display = DisplayConnection {
+            state: raw,
+            since: std::time::Instant::now(),
+        };
+    }
+
+    broadcast(state, DuckMessage::new("connection_display", raw.to_string()));
+}
+
+// Broadcast a connection_status change unless it falls inside the debounce interval
+fn send_connection_status(state: &AppState, connected: bool, message: DuckMessage) {
+    let min_interval = *lock_or_recover(&state.connection_status_debounce);
+    let to_send = lock_or_recover(&state.connection_status_gate).offer(
+        connected,
+        message,
+        std::time::Instant::now(),
+        min_interval,
+    );
+    if let Some(message) = to_send {
+        broadcast(state, message);
+    }
+}
+
+// Send a held connection_status once its debounce interval has passed; called on each monitor tick
+fn flush_connection_status(state: &AppState) {
+    let min_interval = *lock_or_recover(&state.connection_status_debounce);
+    let to_send = lock_or_recover(&state.connection_status_gate).flush(std::time::Instant::now(), min_interval);
+    if let Some(message) = to_send {
+        info!("🔌 Sending debounced connection status: {}", message.message);
+        broadcast(state, message);
+    }
+}
+
+// Count a failed discovery and give up once the configured limit is reached
+fn record_reconnect_failure(state: &AppState) {
+    let Some(max) = *lock_or_recover(&state.max_reconnect_attempts) else {
+        return;
+    };
+
+    let attempts = {
+        let mut attempts = lock_or_recover(&state.reconnect_attempts);
+        *attempts += 1;
+        *attempts
+    };
+    if attempts < max {
+        return;
+    }
+
+    *lock_or_recover(&state.reconnect_gave_up) = true;
+    *lock_or_recover(&state.active_muse_port) = None;
+    warn!("🛑 Giving up on Muse API after {} attempts", attempts);
+    if let Some(sink) = &state.syslog {
+        sink.send(
+            syslog::Severity::Warning,
+            "CONNECTION",
+            &format!("muse connection abandoned after {} attempts", attempts),
+        );
+    }
+    broadcast(state, DuckMessage::new(
+        "connection_abandoned",
+        format!("EEG connection abandoned after {} attempts - reconnect to try again", attempts),
+    ));
+}
+
+// Compare the bridge's capture time against ours. The smallest offset seen since
+// connecting is taken as clock skew, so only delay beyond that counts as age.
+// Emits one `stale_metrics` message per stale episode.
+fn metrics_are_stale(state: &AppState, metrics: &MuseMetrics) -> bool {
+    let Some(max_age) = *lock_or_recover(&state.max_metrics_age_secs) else {
+        return false;
+    };
+    let Some(captured_at) = metrics.captured_at else {
+        return false;
+    };
+
+    let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+    let offset = now - captured_at;
+    let skew = {
+        let mut min_offset = lock_or_recover(&state.clock_offset_secs);
+        let skew = min_offset.map_or(offset, |min| min.min(offset));
+        *min_offset = Some(skew);
+        skew
+    };
+    let age = offset - skew;
+
+    let mut stale = lock_or_recover(&state.metrics_stale);
+    if age <= max_age {
+        *stale = false;
+        return false;
+    }
+
+    if !*stale {
+        *stale = true;
+        debug!("🕰️ Metrics are {:.1}s old (limit {:.1}s), skipping classification", age, max_age);
+        broadcast(state, DuckMessage::new(
+            "stale_metrics",
+            format!("EEG metrics are stale ({:.0}s old) - pausing focus detection", age),
+        ));
+    }
+    true
+}
+
+// Fold a reading into the smoothed score and fire any score alerts it crosses
+fn update_smoothed_score(state: &AppState, focus_score: f64) {
+    if !focus_score.is_finite() {
+        return;
+    }
+
+    let alpha = *lock_or_recover(&state.score_smoothing_alpha);
+    let (previous, current) = {
+        let mut smoothed = lock_or_recover(&state.smoothed_score);
+        let previous = *smoothed;
+        let current = smooth_score(previous, focus_score, alpha);
+        *smoothed = Some(current);
+        (previous, current)
+    };
+    // Expose both scores on the snapshot so the UI can pick one
+    if let Some(latest) = lock_or_recover(&state.latest_metrics).as_mut() {
+        latest.smoothed_focus_score = Some(current);
+    }
+    let Some(previous) = previous else {
+        return;
+    };
+
+    let fired: Vec<ScoreAlert> = {
+        let mut alerts = lock_or_recover(&state.score_alerts);
+        let (fired, armed) = alerts.drain(..).partition(|alert| match alert.direction {
+            AlertDirection::Below => previous >= alert.threshold && current < alert.threshold,
+            AlertDirection::Above => previous <= alert.threshold && current > alert.threshold,
+        });
+        *alerts = armed;
+        fired
+    };
+
+    for alert in fired {
+        let direction = match alert.direction {
+            AlertDirection::Above => "rose above",
+            AlertDirection::Below => "dropped below",
+        };
+        info!("🔔 Score alert {} fired", alert.id);
+        broadcast(state, DuckMessage::new(
+            "score_alert",
+            format!("Focus score {} {:.2} (now {:.2})", direction, alert.threshold, current),
+        ));
+    }
+}
+
+// Turn recent readings into the most likely placement issue plus suggestions
+fn diagnose_samples(connected: bool, stale: bool, recent: &[MuseMetrics]) -> HeadsetDiagnosis {
+    let mut issues: Vec<(String, String)> = Vec::new();
+
+    if !connected || recent.is_empty() {
+        return HeadsetDiagnosis {
+            likely_issue: Some("No readings from the headset".to_string()),
+            suggestions: vec![
+                "Check the Muse is powered on and paired".to_string(),
+                "Make sure the Python backend is running".to_string(),
+            ],
+        };
+    }
+
+    let fraction = |pred: &dyn Fn(&MuseMetrics) -> bool| {
+        recent.iter().filter(|m| pred(m)).count() as f64 / recent.len() as f64
+    };
+    let is_flat = |value: &dyn Fn(&MuseMetrics) -> f64| {
+        let first = value(&recent[0]);
+        recent.iter().all(|m| (value(m) - first).abs() < 1e-9)
+    };
+
+    if stale {
+        issues.push((
+            "Metrics are stale".to_string(),
+            "Restart the Python backend; it may have stopped receiving data".to_string(),
+        ));
+    }
+    if recent.len() > 1
+        && is_flat(&|m| m.focus_score)
+        && is_flat(&|m| m.theta_beta_ratio)
+        && is_flat(&|m| m.movement_intensity)
+    {
+        issues.push((
+            "All signals are flat - the headset may be off your head".to_string(),
+            "Put the headset on and make sure it's streaming".to_string(),
+        ));
+    }
+    if fraction(&|m| m.attention.eq_ignore_ascii_case("unknown")) > 0.5 {
+        issues.push((
+            "EEG can't be classified".to_string(),
+            "Moisten the sensors and tuck the ear sensors behind your ears".to_string(),
+        ));
+    }
+    if fraction(&|m| m.heart_rate <= 0.0) > 0.5 {
+        issues.push((
+            "No heart rate signal".to_string(),
+            "Press the forehead sensor flat against your skin".to_string(),
+        ));
+    }
+    if fraction(&|m| m.movement_intensity > 0.7) > 0.5 {
+        issues.push((
+            "Too much movement".to_string(),
+            "Sit still for a moment so the sensors can settle".to_string(),
+        ));
+    }
+    if fraction(&|m| !m.head_orientation.eq_ignore_ascii_case("center")) > 0.8 {
+        issues.push((
+            "Head consistently turned away from center".to_string(),
+            "Straighten the headset band so it sits level on your forehead".to_string(),
+        ));
+    }
+
+    let likely_issue = issues.first().map(|(issue, _)| issue.clone());
+    let suggestions = issues.into_iter().map(|(_, suggestion)| suggestion).collect();
+    HeadsetDiagnosis { likely_issue, suggestions }
+}
+
+// Plausible metrics for a simulated focus score, labelled like the Python classifier
+fn synthetic_metrics(focus_score: f64) -> MuseMetrics {
+    let attention = if focus_score >= 0.6 {
+        "focused"
+    } else if focus_score >= 0.4 {
+        "neutral"
+    } else {
+        "distracted"
+    };
+
+    MuseMetrics {
+        attention: attention.to_string(),
+        focus_score,
+        brain_state: "simulated".to_string(),
+        head_orientation: "center".to_string(),
+        heart_rate: 70.0,
+        movement_intensity: 0.0,
+        theta_beta_ratio: 2.5 - 1.5 * focus_score,
+        captured_at: None,
+        smoothed_focus_score: None,
+    }
+}
+
+// Add a raw label to the vote window and return the label that wins the N-of-M vote, if any
+fn vote_classification(
+    window: &mut VecDeque<String>,
+    config: VoteConfig,
+    label: &str,
+    committed: Option<&str>,
+) -> Option<String> {
+    window.push_back(label.to_string());
+    while window.len() > config.window {
+        window.pop_front();
+    }
+    leading_label(window, committed)
+        .filter(|(_, votes)| *votes >= config.required)
+        .map(|(label, _)| label)
+}
+
+// Map attention states: labels containing a configured keyword as whole words
+// (so "focus" doesn't match "focused") = unfocused, focused/neutral = focused,
+// anything else, including labels we don't know, = unfocused for safety
+fn focus_state_for(attention: &str, unfocused_keywords: &[String]) -> &'static str {
+    let attention = attention.trim().to_lowercase();
+    let label = label_words(&attention);
+    let matches_keyword = unfocused_keywords.iter().any(|keyword| {
+        let keyword = label_words(keyword);
+        !keyword.is_empty() && label.windows(keyword.len()).any(|run| run == keyword.as_slice())
+    });
+    if matches_keyword {
+        return "unfocused";
+    }
+    match attention.as_str() {
+        "focused" | "neutral" => "focused",
+        _ => "unfocused",
+    }
+}
+
+// Lowercase words of an attention label or keyword, split on anything not alphanumeric
+fn label_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn default_unfocused_keywords() -> Vec<String> {
+    DEFAULT_UNFOCUSED_KEYWORDS.iter().map(|k| k.to_string()).collect()
+}
+
+// One step of the exponential moving average behind the smoothed score
+fn smooth_score(previous: Option<f64>, focus_score: f64, alpha: f64) -> f64 {
+    match previous {
+        Some(prev) => prev + alpha * (focus_score - prev),
+        None => focus_score,
+    }
+}
+
+// Classify a reading taken at `now` and commit a focus state change once it is
+// stable. Shared by the live monitor and simulations.
+fn process_metrics(state: &AppState, metrics: &MuseMetrics, now: std::time::Instant) {
+    let previous_score = lock_or_recover(&state.last_processed_score).replace(metrics.focus_score);
+    update_smoothed_score(state, metrics.focus_score);
+    check_look_away(state, metrics);
+    check_heart_rate(state, metrics.heart_rate);
+    check_movement(state, metrics.movement_intensity);
+
+    // Only accept a classification once it wins the N-of-M vote;
+    // until then keep evaluating the last committed state
+    let previous = lock_or_recover(&state.last_focus_state).clone();
+    let label = {
+        let mode = *lock_or_recover(&state.classification_mode);
+        let hysteresis = *lock_or_recover(&state.focus_hysteresis);
+        mode.label(metrics, &hysteresis, previous.as_deref())
+    };
+    let voted_state = {
+        let config = *lock_or_recover(&state.vote_config);
+        let mut window = lock_or_recover(&state.classification_window);
+        vote_classification(&mut window, config, &label, previous.as_deref())
+    };
+
+    let current_state = match voted_state
+        .or_else(|| lock_or_recover(&state.last_focus_state).clone())
+    {
+        Some(voted) => voted,
+        None => {
+            debug!("🗳️ Waiting for classification agreement (raw: {})", label);
+            return;
+        }
+    };
+
+    debug!("🧠 Current attention state: {} (raw: {}, focus_score: {:.2})",
+             current_state, metrics.attention, metrics.focus_score);
+
+    let mut should_send_message = false;
+    let mut message_to_send: Option<DuckMessage> = None;
+
+    {
+        let mut last_state = lock_or_recover(&state.last_focus_state);
+        let mut last_change = lock_or_recover(&state.last_state_change);
+
+        // Check if state has changed
+        let state_changed = match last_state.as_ref() {
+            Some(prev) => prev != &current_state,
+            None => true,
+        };
+
+        if state_changed {
+            // State changed, reset timer and remember where the score started
+            info!("🔄 State changed to: {}", current_state);
+            *lock_or_recover(&state.change_start_score) = Some(previous_score.unwrap_or(metrics.focus_score));
+            *last_state = Some(current_state.clone());
+            *last_change = Some(now);
+        } else if let Some(change_time) = *last_change {
+            // State has been stable, check if the stability window has passed
+            let elapsed = now.saturating_duration_since(change_time);
+            let stability_window = *lock_or_recover(&state.stability_window);
+
+            if elapsed >= stability_window {
+                // Send message for this state
+                let focus_state = focus_state_for(&current_state, &lock_or_recover(&state.unfocused_keywords));
+
+                info!("⏰ State '{}' stable for {:.1}s, mapped to: {}", current_state, stability_window.as_secs_f64(), focus_state);
+
+                // Drowsiness gets its own cooldown even though it nudges as unfocused
+                let cooldown_kind = if current_state.eq_ignore_ascii_case("drowsy") {
+                    "drowsy"
+                } else {
+                    focus_state
+                };
+                let cooldown_secs = lock_or_recover(&state.state_cooldowns).secs_for(cooldown_kind);
+                let mut last_nudge_at = lock_or_recover(&state.last_nudge_at);
+                if let Some(sent_at) = last_nudge_at.get(cooldown_kind) {
+                    let since = now.saturating_duration_since(*sent_at).as_secs_f64();
+                    if since < cooldown_secs {
+                        debug!("⏲️ '{}' nudge on cooldown ({:.1}s of {:.1}s)", cooldown_kind, since, cooldown_secs);
+                        *last_change = None;
+                        return;
+                    }
+                }
+                last_nudge_at.insert(cooldown_kind.to_string(), now);
+                drop(last_nudge_at);
+
+                message_to_send = Some(focus_change_message(focus_state, metrics));
+
+                should_send_message = true;
+
+                // Reset timer so we don't send duplicate messages
+                *last_change = None;
+            } else {
+                debug!("⏳ State stable, waiting... ({:.1}s elapsed)", elapsed.as_secs_f32());
+            }
+        }
+    }
+
+    if should_send_message {
+        if let Some(msg) = message_to_send {
+            persist_state_change(state, &msg, metrics);
+            dispatch_focus_change(state, msg, metrics);
+        }
+    }
+}
+
+// The focus_state_change message sent when a state is committed
+fn focus_change_message(focus_state: &str, metrics: &MuseMetrics) -> DuckMessage {
+    let message = if focus_state == "unfocused" {
+        "⚠️ Distraction detected! Duck spawned.".to_string()
+    } else {
+        "✅ Focus restored!".to_string()
+    };
+
+    DuckMessage {
+        focus_state: Some(focus_state.to_string()),
+        metrics: Some(MessageMetrics {
+            attention: metrics.attention.clone(),
+            focus_score: metrics.focus_score,
+            brain_state: metrics.brain_state.clone(),
+        }),
+        event_id: Some(uuid::Uuid::new_v4().to_string()),
+        ..DuckMessage::new("focus_state_change", message)
+    }
+}
+
+// Write a committed focus state change to the session log; failures are only logged
+fn persist_state_change(state: &AppState, msg: &DuckMessage, metrics: &MuseMetrics) {
+    let Some(focus_state) = msg.focus_state.as_deref() else {
+        return;
+    };
+    let db = lock_or_recover(&state.db);
+    let Some(conn) = db.as_ref() else {
+        return;
+    };
+
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    if let Err(e) = storage::log_state_change(conn, focus_state, &timestamp, metrics.focus_score, &metrics.brain_state) {
+        warn!("⚠️ Failed to log state change: {}", e);
+    }
+}
+
+// Add the time since the last tick to the monitored (and, if connected, connected) totals
+fn record_connection_uptime(state: &AppState) {
+    let connected = *lock_or_recover(&state.muse_connected);
+    let mut uptime = lock_or_recover(&state.connection_uptime);
+    let now = std::time::Instant::now();
+
+    if let Some(last_tick) = uptime.last_tick {
+        let elapsed = now.duration_since(last_tick).as_secs_f64();
+        uptime.monitored_secs += elapsed;
+        if connected {
+            uptime.connected_secs += elapsed;
+        }
+    }
+    uptime.last_tick = Some(now);
+}
+
+// Save the discovered port so the next launch tries it first
+fn remember_muse_port(state: &AppState, port: u16) {
+    if !state.remember_muse_port {
+        return;
+    }
+
+    let mut last_port = lock_or_recover(&state.last_muse_port);
+    if *last_port == Some(port) {
+        return;
+    }
+    *last_port = Some(port);
+
+    if let Some(path) = &state.config_path {
+        if let Err(e) = config::update_config_key(path, "last_muse_port", serde_json::json!(port)) {
+            warn!("⚠️ Failed to save last Muse port: {}", e);
+        }
+    }
+}
+
+// Parse a bridge response with the configured schema, auto-detecting when needed
+fn parse_metrics(state: &AppState, body: &serde_json::Value) -> Option<MuseMetrics> {
+    let configured = *lock_or_recover(&state.muse_schema);
+    if configured != MuseSchema::Auto {
+        return configured.parse(body);
+    }
+
+    let mut detected = lock_or_recover(&state.detected_schema);
+    if let Some(metrics) = detected.and_then(|schema| schema.parse(body)) {
+        return Some(metrics);
+    }
+
+    let (schema, metrics) = MuseSchema::detect(body)?;
+    info!("🧩 Detected Muse schema: {:?}", schema);
+    *detected = Some(schema);
+    Some(metrics)
+}
+
+// Track iteration duration and warn when the loop keeps overrunning its cadence
+fn record_loop_iteration(state: &AppState, elapsed: std::time::Duration) {
+    let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+    let target_ms = lock_or_recover(&state.poll_interval).as_millis() as u64;
+    let mut stats = lock_or_recover(&state.loop_stats);
+
+    if stats.recent_ms.len() >= LOOP_TIMING_WINDOW {
+        stats.recent_ms.pop_front();
+    }
+    stats.recent_ms.push_back(elapsed_ms);
+    stats.max_ms = stats.max_ms.max(elapsed_ms);
+
+    if elapsed_ms > target_ms as f64 {
+        stats.overruns += 1;
+        stats.consecutive_overruns += 1;
+        if stats.consecutive_overruns == LOOP_OVERRUN_WARN_STREAK {
+            warn!(
+                "⚠️ Monitor loop falling behind: {} iterations in a row over {}ms (last {:.0}ms)",
+                LOOP_OVERRUN_WARN_STREAK, target_ms, elapsed_ms
+            );
+        }
+    } else {
+        stats.consecutive_overruns = 0;
+    }
+}
+
+// Write a sample to the history database under the currently committed focus state.
+// Failures are logged and never interrupt monitoring.
+fn persist_sample(state: &AppState, metrics: &MuseMetrics, duration_secs: f64) {
+    let db = lock_or_recover(&state.db);
+    let Some(conn) = db.as_ref() else {
+        return;
+    };
+
+    let focus_state = lock_or_recover(&state.focus_transitions)
+        .back()
+        .map(|t| t.focus_state.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let session_id = lock_or_recover(&state.session_id).clone();
+    let session_tag = lock_or_recover(&state.session_tag).clone();
+
+    if let Err(e) = storage::log_sample(
+        conn,
+        &timestamp,
+        &focus_state,
+        metrics.focus_score,
+        metrics.heart_rate,
+        duration_secs,
+        storage::SessionInfo {
+            id: &session_id,
+            tag: session_tag.as_deref(),
+        },
+    ) {
+        warn!("⚠️ Failed to persist sample: {}", e);
+    }
+}
+
+// Write `[time] Focused → Unfocused (score 0.32)` to the transition log, if enabled.
+// The file is reopened per line so external rotation is picked up; errors are only logged.
+fn append_transition_log(state: &AppState, from: Option<&str>, to: &str, focus_score: f64) {
+    let Some(path) = lock_or_recover(&state.transition_log_path).clone() else {
+        return;
+    };
+
+    let capitalize = |s: &str| {
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        }
+    };
+    let line = format!(
+        "[{}] {} → {} (score {:.2})",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        capitalize(from.unwrap_or("unknown")),
+        capitalize(to),
+        focus_score
+    );
+
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() >= TRANSITION_LOG_MAX_BYTES) {
+        let rotated = PathBuf::from(format!("{}.1", path.display()));
+        if let Err(e) = std::fs::rename(&path, &rotated) {
+            warn!("⚠️ Failed to rotate transition log: {}", e);
+        }
+    }
+
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        warn!("⚠️ Failed to write transition log {}: {}", path.display(), e);
+    }
+}
+
+// Send a message to the Tauri frontend and all WebSocket clients
+fn broadcast(state: &AppState, message: DuckMessage) {
+    // Numbering and sending under one lock keeps channel order in sequence order
+    let _order = lock_or_recover(&state.broadcast_order);
+    let message = prioritize(state, message);
+    if let Some(active) = lock_or_recover(&state.recording).as_mut() {
+        active.write_entry("message", &message);
+    }
+    if message.priority == Some(PRIORITY_IMPORTANT) {
+        record_pending_alert(state, &message);
+    }
+    if let Some(Err(e)) = emit_to_frontend(state, "duck-message", message.clone()) {
+        queue_emit_retry(state, &message, &e);
+    }
+    let _ = state.ws_tx.send(message);
+}
+
+// Emit an event to the frontend, tracking consecutive failures. None when
+// nothing was attempted: no window, or emits are suspended after too many failures.
+fn emit_to_frontend<S: Serialize + Clone>(state: &AppState, event: &str, payload: S) -> Option<Result<(), String>> {
+    if lock_or_recover(&state.frontend_emit).suspended {
+        return None;
+    }
+    let result = lock_or_recover(&state.tauri_handle)
+        .as_ref()?
+        .emit(event, payload)
+        .map_err(|e| e.to_string());
+
+    let mut health = lock_or_recover(&state.frontend_emit);
+    match &result {
+        Ok(()) => health.consecutive_failures = 0,
+        Err(e) => {
+            health.consecutive_failures += 1;
+            if health.consecutive_failures >= FRONTEND_EMIT_FAILURE_THRESHOLD {
+                health.suspended = true;
+                warn!(
+                    "⚠️ Frontend appears unavailable after {} failed emits (last: {}); pausing emits until a window loads",
+                    health.consecutive_failures, e
+                );
+            }
+        }
+    }
+    Some(result)
+}
+
+// A page (re)loaded and registered its listeners; try emitting again
+fn resume_frontend_emits(state: &AppState) {
+    let mut health = lock_or_recover(&state.frontend_emit);
+    if health.suspended {
+        info!("🪟 Frontend window loaded, resuming emits");
+    }
+    *health = FrontendEmitHealth::default();
+}
+
+// Hold a failed frontend emit for retry if its type is configured for it
+fn queue_emit_retry(state: &AppState, message: &DuckMessage, error: &str) {
+    let config = lock_or_recover(&state.emit_retry_config).clone();
+    let mut retry = lock_or_recover(&state.emit_retry);
+    if config.max_attempts == 0 || !config.msg_types.contains(&message.msg_type) {
+        retry.dropped += 1;
+        warn!("⚠️ Frontend emit failed, dropping {}: {}", message.msg_type, error);
+        return;
+    }
+
+    warn!("⚠️ Frontend emit failed, will retry {}: {}", message.msg_type, error);
+    if retry.queue.len() >= EMIT_RETRY_QUEUE_SIZE {
+        retry.queue.pop_front();
+        retry.dropped += 1;
+    }
+    retry.queue.push_back((message.clone(), config.max_attempts));
+}
+
+// Re-send queued frontend emits; called on each monitor tick
+fn retry_failed_emits(state: &AppState) {
+    let pending: Vec<(DuckMessage, u32)> = lock_or_recover(&state.emit_retry).queue.drain(..).collect();
+    if pending.is_empty() {
+        return;
+    }
+
+    let mut retry = lock_or_recover(&state.emit_retry);
+    for (message, attempts_left) in pending {
+        let delivered = matches!(emit_to_frontend(state, "duck-message", message.clone()), Some(Ok(())));
+        if delivered {
+            retry.retried += 1;
+        } else if attempts_left > 1 {
+            retry.queue.push_back((message, attempts_left - 1));
+        } else {
+            retry.dropped += 1;
+            warn!("⚠️ Giving up on frontend emit of {}", message.msg_type);
+        }
+    }
+}
+
+// Keep an important message in the inbox until it is acknowledged or expires
+fn record_pending_alert(state: &AppState, message: &DuckMessage) {
+    let id = {
+        let mut next_id = lock_or_recover(&state.next_pending_alert_id);
+        *next_id += 1;
+        *next_id
+    };
+    let ttl = *lock_or_recover(&state.alert_ttl_secs);
+    let now = chrono::Utc::now();
+    let expires = now + chrono::Duration::seconds(ttl as i64);
+
+    let mut alerts = lock_or_recover(&state.pending_alerts);
+    alerts.retain(|alert| alert.expires > now);
+    if alerts.len() >= PENDING_ALERTS_SIZE {
+        alerts.pop_front();
+    }
+    alerts.push_back(Alert {
+        id,
+        kind: message.msg_type.clone(),
+        message: message.message.clone(),
+        timestamp: message.timestamp.clone(),
+        expires_at: expires.to_rfc3339(),
+        expires,
+    });
+}
+
+// Number an outgoing message and fill in a missing priority from the map,
+// preferring a `type:focus_state` entry
+fn prioritize(state: &AppState, mut message: DuckMessage) -> DuckMessage {
+    message.seq = Some(state.next_seq.fetch_add(1, Ordering::Relaxed));
+    if message.priority.is_none() {
+        let priorities = lock_or_recover(&state.message_priorities);
+        message.priority = message
+            .focus_state
+            .as_ref()
+            .and_then(|focus| priorities.get(&format!("{}:{}", message.msg_type, focus)))
+            .or_else(|| priorities.get(&message.msg_type))
+            .copied();
+    }
+    message
+}
+
+// Number a message that bypasses the broadcast channel under the broadcast lock,
+// so it can't take a seq lower than one already handed to the channel
+fn prioritize_ordered(state: &AppState, message: DuckMessage) -> DuckMessage {
+    let _order = lock_or_recover(&state.broadcast_order);
+    prioritize(state, message)
+}
+
+// Record a committed focus state change and deliver it to clients
+fn dispatch_focus_change(state: &AppState, msg: DuckMessage, metrics: &MuseMetrics) {
+    debug!("📤 Sending focus state message: {:?}", msg);
+
+    // Remember the transition for later correlation
+    if let Some(focus_state) = msg.focus_state.clone() {
+        let previous_state = {
+            let mut transitions = lock_or_recover(&state.focus_transitions);
+            let previous_state = transitions.back().map(|t| t.focus_state.clone());
+            if transitions.len() >= TRANSITION_HISTORY_SIZE {
+                transitions.pop_front();
+            }
+            let score_before = lock_or_recover(&state.change_start_score)
+                .take()
+                .unwrap_or(metrics.focus_score);
+            transitions.push_back(FocusTransition {
+                at: chrono::Utc::now(),
+                focus_state: focus_state.clone(),
+                focus_score: metrics.focus_score,
+                score_before,
+                message: msg.clone(),
+            });
+            previous_state
+        };
+
+        if let Some(session) = lock_or_recover(&state.focus_session).as_mut() {
+            session.transition(Some(&focus_state), std::time::Instant::now());
+            session.transitions.push(SessionTransition {
+                timestamp: msg.timestamp.clone(),
+                focus_state: focus_state.clone(),
+                focus_score: metrics.focus_score,
+                heart_rate: metrics.heart_rate,
+                theta_beta_ratio: metrics.theta_beta_ratio,
+                movement_intensity: metrics.movement_intensity,
+            });
+        }
+
+        append_transition_log(state, previous_state.as_deref(), &focus_state, metrics.focus_score);
+        if let Some(sink) = &state.syslog {
+            sink.send(
+                syslog::Severity::Info,
+                "FOCUS",
+                &format!(
+                    "focus {} -> {} score={:.2}",
+                    previous_state.as_deref().unwrap_or("none"),
+                    focus_state,
+                    metrics.focus_score
+                ),
+            );
+        }
+    }
+
+    // Hold nudges while a fullscreen app is active, telling clients once
+    if *lock_or_recover(&state.fullscreen_active) {
+        let mut notified = lock_or_recover(&state.suppression_notified);
+        if !*notified {
+            *notified = true;
+            info!("🔕 Fullscreen active, suppressing duck nudges");
+            broadcast(state, DuckMessage::new(
+                "nudges_suppressed",
+                "Fullscreen app active - duck nudges paused",
+            ));
+        }
+        return;
+    }
+
+    record_message(state);
+
+    if msg.focus_state.as_deref() != Some("unfocused") {
+        broadcast(state, msg);
+        return;
+    }
+
+    // Distraction nudges go out at once; the ack clock starts when they do
+    let event_id = msg.event_id.clone();
+    broadcast(state, absorb_pending_nudge(state, msg));
+    if let Some(event_id) = event_id {
+        let mut unacked = lock_or_recover(&state.unacked_distractions);
+        if unacked.len() >= DISTRACTION_ACK_BUFFER_SIZE {
+            unacked.pop_front();
+        }
+        unacked.push_back((event_id, std::time::Instant::now()));
+    }
+}
+
+// Fold an alert still held for the merge window into a distraction nudge. The
+// nudge keeps its focus_state_change type and lists every condition.
+fn absorb_pending_nudge(state: &AppState, mut msg: DuckMessage) -> DuckMessage {
+    let Some(nudge) = lock_or_recover(&state.pending_nudge).take() else {
+        return msg;
+    };
+    let mut conditions = vec!["distracted".to_string()];
+    conditions.extend(nudge.conditions.into_iter().filter(|c| c != "distracted"));
+    info!("🧩 Merging alerts into distraction nudge: {:?}", conditions);
+    msg.metrics = msg.metrics.or(nudge.message.metrics);
+    msg.conditions = Some(conditions);
+    msg
+}
+
+// Zone after a new reading. Leaving High/Low needs the rate to come back inside
+// the bounds by the hysteresis margin; entering them only needs crossing.
+fn next_heart_rate_zone(current: HeartRateZone, bpm: f64, thresholds: HeartRateThresholds) -> HeartRateZone {
+    match current {
+        HeartRateZone::High if bpm > thresholds.high - thresholds.hysteresis => HeartRateZone::High,
+        HeartRateZone::Low if bpm < thresholds.low + thresholds.hysteresis => HeartRateZone::Low,
+        _ if bpm > thresholds.high => HeartRateZone::High,
+        _ if bpm < thresholds.low => HeartRateZone::Low,
+        _ => HeartRateZone::Normal,
+    }
+}
+
+// Emit a heart_rate_alert when the rate leaves the configured range
+fn check_heart_rate(state: &AppState, bpm: f64) {
+    // 0 means the headset has no PPG reading yet
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return;
+    }
+
+    let thresholds = *lock_or_recover(&state.heart_rate_thresholds);
+    let zone = {
+        let mut zone = lock_or_recover(&state.heart_rate_zone);
+        let next = next_heart_rate_zone(*zone, bpm, thresholds);
+        if next == *zone {
+            return;
+        }
+        *zone = next;
+        next
+    };
+
+    let message = match zone {
+        HeartRateZone::High => format!("❤️ Heart rate high: {:.0} bpm", bpm),
+        HeartRateZone::Low => format!("❤️ Heart rate low: {:.0} bpm", bpm),
+        HeartRateZone::Normal => {
+            info!("❤️ Heart rate back in range ({:.0} bpm)", bpm);
+            return;
+        }
+    };
+    info!("{}", message);
+    broadcast(state, DuckMessage::new("heart_rate_alert", message));
+}
+
+// Raise one movement_alert per episode of movement held above the configured threshold
+fn check_movement(state: &AppState, intensity: f64) {
+    let config = *lock_or_recover(&state.movement_alert);
+    let mut episode = lock_or_recover(&state.fidget_episode);
+    if !intensity.is_finite() || intensity <= config.threshold {
+        *episode = None;
+        return;
+    }
+
+    let current = episode.get_or_insert(FidgetEpisode {
+        since: std::time::Instant::now(),
+        peak: intensity,
+        notified: false,
+    });
+    current.peak = current.peak.max(intensity);
+    if current.notified || current.since.elapsed().as_secs_f64() < config.dwell_secs {
+        return;
+    }
+    current.notified = true;
+    let peak = current.peak;
+    drop(episode);
+
+    info!("🌀 Sustained movement (peak {:.2})", peak);
+    raise_alert(state, "fidgeting", DuckMessage::new(
+        "movement_alert",
+        format!("🌀 Lots of fidgeting - peak movement {:.0}%", peak * 100.0),
+    ));
+}
+
+// Raise a look-away alert once the head has been off-center for LOOK_AWAY_SECS
+fn check_look_away(state: &AppState, metrics: &MuseMetrics) {
+    let mut since = lock_or_recover(&state.look_away_since);
+    let mut notified = lock_or_recover(&state.look_away_notified);
+    if !matches!(metrics.head_orientation.as_str(), "left" | "right") {
+        *since = None;
+        *notified = false;
+        return;
+    }
+
+    let started = *since.get_or_insert_with(std::time::Instant::now);
+    if *notified || started.elapsed().as_secs_f64() < LOOK_AWAY_SECS {
+        return;
+    }
+    *notified = true;
+    drop(notified);
+    drop(since);
+
+    info!("👀 Looking away ({})", metrics.head_orientation);
+    raise_alert(state, "look_away", DuckMessage {
+        metrics: Some(MessageMetrics {
+            attention: metrics.attention.clone(),
+            focus_score: metrics.focus_score,
+            brain_state: metrics.brain_state.clone(),
+        }),
+        ..DuckMessage::new("look_away", "👀 Looking away from the screen")
+    });
+}
+
+// Send an alert, or hold it for the merge window so that alerts firing together
+// go out as one composite message
+fn raise_alert(state: &AppState, condition: &str, msg: DuckMessage) {
+    let window_ms = *lock_or_recover(&state.alert_merge_window_ms);
+    if window_ms == 0 {
+        broadcast(state, msg);
+        return;
+    }
+
+    let mut pending = lock_or_recover(&state.pending_nudge);
+    match pending.as_mut() {
+        Some(nudge) => {
+            if !nudge.conditions.iter().any(|c| c == condition) {
+                nudge.conditions.push(condition.to_string());
+            }
+            nudge.message.focus_state = nudge.message.focus_state.take().or(msg.focus_state);
+            nudge.message.event_id = nudge.message.event_id.take().or(msg.event_id);
+            nudge.message.metrics = msg.metrics.or(nudge.message.metrics.take());
+        }
+        None => {
+            *pending = Some(PendingNudge {
+                conditions: vec![condition.to_string()],
+                message: msg,
+                deadline: std::time::Instant::now() + std::time::Duration::from_millis(window_ms),
+            });
+        }
+    }
+}
+
+// Deliver a held alert once its merge window has passed
+fn flush_pending_nudge(state: &AppState) {
+    let nudge = {
+        let mut pending = lock_or_recover(&state.pending_nudge);
+        match pending.as_ref() {
+            Some(nudge) if std::time::Instant::now() >= nudge.deadline => pending.take(),
+            _ => None,
+        }
+    };
+    let Some(nudge) = nudge else {
+        return;
+    };
+
+    let msg = if nudge.conditions.len() > 1 {
+        info!("🧩 Merging alerts: {:?}", nudge.conditions);
+        DuckMessage {
+            message: format!("⚠️ {}", composite_alert_text(&nudge.conditions)),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            msg_type: "composite_alert".to_string(),
+            priority: None,
+            conditions: Some(nudge.conditions),
+            ..nudge.message
+        }
+    } else {
+        nudge.message
+    };
+    broadcast(state, msg);
+}
+
+// "Distracted and looking away" from the merged condition names
+fn composite_alert_text(conditions: &[String]) -> String {
+    let labels: Vec<&str> = conditions
+        .iter()
+        .map(|c| match c.as_str() {
+            "distracted" => "distracted",
+            "look_away" => "looking away",
+            "fidgeting" => "fidgeting",
+            other => other,
+        })
+        .collect();
+    let text = match labels.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => labels.join(""),
+    };
+
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+// Whether `failures` consecutive failed polls should flip the headset to disconnected
+fn reached_disconnect_threshold(failures: u32, threshold: u32) -> bool {
+    failures >= threshold.max(1)
+}
+
+async fn handle_muse_failure(state: &AppState, last_message_sent: &mut bool, reason: &str) {
+    let mut connected = lock_or_recover(&state.muse_connected);
+    let mut failures = lock_or_recover(&state.consecutive_failures);
+
+    *failures += 1;
+
+    // Only mark as disconnected and send message after enough consecutive failures
+    // This prevents flapping on temporary network issues
+    let threshold = *lock_or_recover(&state.disconnect_threshold);
+    if *connected && reached_disconnect_threshold(*failures, threshold) {
+        warn!("❌ Muse EEG disconnected: {}", reason);
+        if let Some(sink) = &state.syslog {
+            sink.send(syslog::Severity::Warning, "CONNECTION", &format!("muse disconnected: {}", reason));
+        }
+        *connected = false;
+        *last_message_sent = false;
+
+        // Clear focus state since we can't monitor anymore
+        *lock_or_recover(&state.last_focus_state) = None;
+        *lock_or_recover(&state.last_state_change) = None;
+        lock_or_recover(&state.classification_window).clear();
+        *lock_or_recover(&state.last_processed_score) = None;
+        *lock_or_recover(&state.change_start_score) = None;
+        *lock_or_recover(&state.clock_offset_secs) = None;
+        *lock_or_recover(&state.smoothed_score) = None;
+        *lock_or_recover(&state.look_away_since) = None;
+        *lock_or_recover(&state.look_away_notified) = false;
+        *lock_or_recover(&state.latest_metrics) = None;
+        *lock_or_recover(&state.heart_rate_zone) = HeartRateZone::Normal;
+        *lock_or_recover(&state.fidget_episode) = None;
+        if let Some(session) = lock_or_recover(&state.focus_session).as_mut() {
+            session.transition(None, std::time::Instant::now());
+        }
+    }
+
+    // Send disconnection message only once
+    if !*connected && !*last_message_sent {
+        let disconn_msg = DuckMessage::new(
+            "connection_status",
+            "EEG Disconnected - Please connect your Muse headset",
+        );
+
+        send_connection_status(state, false, disconn_msg);
+
+        *last_message_sent = true;
+    }
+}
+
+// Resolve the on-disk locations used by the app via the Tauri path API
+fn resolve_data_paths(app_handle: &tauri::AppHandle) -> Result<DataPaths, String> {
+    let paths = app_handle.path();
+    let config_dir = paths
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config dir: {}", e))?;
+    let data_dir = paths
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve data dir: {}", e))?;
+    let log_dir = paths
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log dir: {}", e))?;
+
+    Ok(DataPaths {
+        config_file: config_dir.join(CONFIG_FILE_NAME),
+        config_dir,
+        db_path: data_dir.join(DB_FILE_NAME),
+        history_file: data_dir.join(HISTORY_FILE_NAME),
+        data_dir,
+        log_dir,
+    })
+}
+
+// Without Tauri, keep everything under DUCK_DATA_DIR (default ./duck-data)
+fn headless_data_paths() -> Result<DataPaths, String> {
+    let base = std::env::var_os(HEADLESS_DATA_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(HEADLESS_DEFAULT_DATA_DIR));
+    let base = std::path::absolute(&base)
+        .map_err(|e| format!("Failed to resolve data dir {}: {}", base.display(), e))?;
+
+    Ok(DataPaths {
+        config_file: base.join(CONFIG_FILE_NAME),
+        config_dir: base.clone(),
+        db_path: base.join(DB_FILE_NAME),
+        history_file: base.join(HISTORY_FILE_NAME),
+        log_dir: base.join("logs"),
+        data_dir: base,
+    })
+}
+
+// Locate the directory holding the backend script. PYTHON_BACKEND_DIR wins;
+// otherwise packaged builds look in the bundled resource dir first and dev
+// builds in ../python-backend next to the working directory first.
+fn resolve_python_dir(app_handle: Option<&tauri::AppHandle>, script: &str) -> Result<PathBuf, io::Error> {
+    if let Some(dir) = std::env::var_os(PYTHON_BACKEND_DIR_ENV) {
+        let dir = PathBuf::from(dir);
+        if !dir.join(script).is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found in {} (from {})", script, dir.display(), PYTHON_BACKEND_DIR_ENV),
+            ));
+        }
+        return Ok(dir);
+    }
+
+    let from_resources = app_handle.and_then(|handle| match handle.path().resource_dir() {
+        Ok(dir) => Some(dir.join("python-backend")),
+        Err(e) => {
+            println!("⚠️ Cannot resolve resource dir: {}", e);
+            None
+        }
+    });
+    let from_cwd = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| cwd.parent().map(|parent| parent.join("python-backend")));
+    let candidates: Vec<PathBuf> = if cfg!(debug_assertions) {
+        [from_cwd, from_resources]
+    } else {
+        [from_resources, from_cwd]
+    }
+    .into_iter()
+    .flatten()
+    .collect();
+
+    candidates
+        .iter()
+        .find(|dir| dir.join(script).is_file())
+        .cloned()
+        .ok_or_else(|| {
+            let tried: Vec<String> = candidates.iter().map(|dir| dir.join(script).display().to_string()).collect();
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} not found (tried: {}); set {} to the backend directory",
+                    script,
+                    if tried.is_empty() { "no candidate paths".to_string() } else { tried.join(", ") },
+                    PYTHON_BACKEND_DIR_ENV
+                ),
+            )
+        })
+}
+
+// Store a log line in the ring buffer, notify live streams and, if enabled,
+// forward it to clients
+fn record_log_line(state: &AppState, source: &str, line: String) {
+    let entry = LogLine {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        source: source.to_string(),
+        line,
+    };
+
+    {
+        let mut logs = lock_or_recover(&state.log_lines);
+        if logs.len() >= LOG_BUFFER_SIZE {
+            logs.pop_front();
+        }
+        logs.push_back(entry.clone());
+    }
+
+    if *lock_or_recover(&state.forward_backend_logs) {
+        broadcast(state, DuckMessage {
+            timestamp: entry.timestamp.clone(),
+            ..DuckMessage::new("backend_log", entry.line.clone())
+        });
+    }
+
+    let _ = state.log_tx.send(entry);
+}
+
+// Forward the Python child's stdout/stderr into the log buffer
+fn capture_backend_output(state: &AppState) {
+    let mut process = lock_or_recover(&state.python_process);
+    let Some(child) = process.as_mut() else {
+        return;
+    };
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_log_reader(state.clone(), "stdout", stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_log_reader(state.clone(), "stderr", stderr);
+    }
+}
+
+// Read lines of at most MAX_LOG_LINE_BYTES until the stream closes. stderr
+// (where Python tracebacks go) is logged as a warning, stdout as info.
+fn spawn_log_reader<R: io::Read + Send + 'static>(state: AppState, source: &'static str, reader: R) {
+    std::thread::spawn(move || {
+        let mut reader = io::BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match (&mut reader).take(MAX_LOG_LINE_BYTES as u64).read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("⚠️ Stopped reading backend {}: {}", source, e);
+                    break;
+                }
+            }
+
+            let line = String::from_utf8_lossy(&buf).trim_end_matches(['\r', '\n']).to_string();
+            if source == "stderr" {
+                warn!(target: "python", "🐍 {}", line);
+            } else {
+                info!(target: "python", "🐍 {}", line);
+            }
+            record_log_line(&state, source, line);
+        }
+    });
+}
 
-                            // Check if state has changed
-                            let state_changed = match last_state.as_ref() {
-                                Some(prev) => prev != &current_state,
-                                None => true,
-                            };
+// Watch the Python child and relaunch it with backoff when it exits, giving up
+// after MAX_PYTHON_RESTARTS within PYTHON_RESTART_WINDOW_SECS
+async fn supervise_python_backend(state: AppState) {
+    let window = std::time::Duration::from_secs(PYTHON_RESTART_WINDOW_SECS);
+    let mut recent_restarts: VecDeque<std::time::Instant> = VecDeque::new();
 
-                            if state_changed {
-                                // State changed, reset timer
-                                println!("🔄 State changed to: {}", current_state);
-                                *last_state = Some(current_state.clone());
-                                *last_change = Some(std::time::Instant::now());
-                            } else if let Some(change_time) = *last_change {
-                                // State has been stable, check if 2 seconds have passed
-                                let elapsed = change_time.elapsed();
-
-                                if elapsed.as_secs() >= 2 {
-                                    // Send message for this state
-                                    // Map attention states: focused/neutral = focused, distracted/drowsy/unknown = unfocused
-                                    let focus_state = match current_state.to_lowercase().as_str() {
-                                        "focused" | "neutral" => "focused",
-                                        "distracted" | "drowsy" | "unknown" => "unfocused",
-                                        _ => "unfocused"  // Default to unfocused for safety
-                                    };
-
-                                    println!("⏰ State '{}' stable for 2s, mapped to: {}", current_state, focus_state);
-
-                                    let message = if focus_state == "unfocused" {
-                                        "⚠️ Distraction detected! Duck spawned.".to_string()
-                                    } else {
-                                        "✅ Focus restored!".to_string()
-                                    };
-
-                                    message_to_send = Some(DuckMessage {
-                                        message,
-                                        timestamp: chrono::Utc::now().to_rfc3339(),
-                                        msg_type: "focus_state_change".to_string(),
-                                        focus_state: Some(focus_state.to_string()),
-                                        metrics: Some(MessageMetrics {
-                                            attention: metrics.attention.clone(),
-                                            focus_score: metrics.focus_score,
-                                            brain_state: metrics.brain_state.clone(),
-                                        }),
-                                    });
-
-                                    should_send_message = true;
-
-                                    // Reset timer so we don't send duplicate messages
-                                    *last_change = None;
-                                } else {
-                                    println!("⏳ State stable, waiting... ({:.1}s elapsed)", elapsed.as_secs_f32());
-                                }
-                            }
-                        }
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(PYTHON_SUPERVISOR_INTERVAL_SECS)) => {}
+            _ = shutdown_signal(state.clone()) => return,
+        }
 
-                        if should_send_message {
-                            if let Some(msg) = message_to_send {
-                                println!("📤 Sending focus state message: {:?}", msg);
+        let exit_status = {
+            let mut process = lock_or_recover(&state.python_process);
+            // Taken on shutdown
+            let Some(child) = process.as_mut() else {
+                return;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    process.take();
+                    status
+                }
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("⚠️ Failed to check Python backend: {}", e);
+                    continue;
+                }
+            }
+        };
+        warn!("💀 Python backend exited ({})", exit_status);
 
-                                // Increment counter
-                                {
-                                    let mut count = state.message_count.lock().unwrap();
-                                    *count += 1;
-                                }
+        loop {
+            let now = std::time::Instant::now();
+            while recent_restarts.front().is_some_and(|t| now.duration_since(*t) > window) {
+                recent_restarts.pop_front();
+            }
+            if recent_restarts.len() >= MAX_PYTHON_RESTARTS {
+                error!("❌ Python backend crashed {} times in {}s, giving up", MAX_PYTHON_RESTARTS, PYTHON_RESTART_WINDOW_SECS);
+                broadcast(&state, DuckMessage::new(
+                    "backend_fatal",
+                    "Python backend keeps crashing - check the backend logs and restart the app",
+                ));
+                return;
+            }
 
-                                // Emit to Tauri frontend
-                                if let Some(app) = state.tauri_handle.lock().unwrap().as_ref() {
-                                    let _ = app.emit("duck-message", msg.clone());
-                                }
+            let backoff = PYTHON_RESTART_BASE_BACKOFF_SECS << recent_restarts.len();
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(backoff)) => {}
+                _ = shutdown_signal(state.clone()) => return,
+            }
+            recent_restarts.push_back(std::time::Instant::now());
 
-                                // Broadcast to WebSocket clients (browser extension)
-                                let _ = state.ws_tx.send(msg);
-                            }
-                        }
-                    } else {
-                        // JSON parsing failed - API might not be ready
-                        handle_muse_failure(&state, &mut last_connection_message_sent, "Invalid response from Muse API").await;
-                    }
-                } else {
-                    // Non-200 status - port might have changed
-                    println!("⚠️ Lost connection, rediscovering port...");
-                    muse_port = None;
-                    handle_muse_failure(&state, &mut last_connection_message_sent, "Connection lost").await;
+            let app_handle = lock_or_recover(&state.tauri_handle).clone();
+            match launch_python_backend(app_handle.as_ref(), state.server_port) {
+                Ok(child) => {
+                    *lock_or_recover(&state.python_process) = Some(child);
+                    capture_backend_output(&state);
+                    let restarts = {
+                        let mut restarts = lock_or_recover(&state.backend_restarts);
+                        *restarts += 1;
+                        *restarts
+                    };
+                    info!("🔁 Python backend restarted (restart #{})", restarts);
+                    broadcast(&state, DuckMessage::new(
+                        "backend_restart",
+                        format!("Python backend restarted (restart #{})", restarts),
+                    ));
+                    break;
                 }
-            }
-            Err(_) => {
-                // Connection error - port might have changed
-                println!("⚠️ Connection error, rediscovering port...");
-                muse_port = None;
-                handle_muse_failure(&state, &mut last_connection_message_sent, "Connection error").await;
+                Err(e) => error!("❌ Failed to relaunch Python backend: {}", e),
             }
         }
     }
 }
 
-async fn handle_muse_failure(state: &AppState, last_message_sent: &mut bool, reason: &str) {
-    let mut connected = state.muse_connected.lock().unwrap();
-    let mut failures = state.consecutive_failures.lock().unwrap();
+// Non-empty value of `name`, or `default`
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
 
-    *failures += 1;
+fn skip_python_launch() -> bool {
+    env_flag(SKIP_PYTHON_LAUNCH_ENV)
+}
 
-    // Only mark as disconnected and send message after 5 consecutive failures
-    // This prevents flapping on temporary network issues
-    if *failures >= 5 && *connected {
-        println!("❌ Muse EEG disconnected: {}", reason);
-        *connected = false;
-        *last_message_sent = false;
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
 
-        // Clear focus state since we can't monitor anymore
-        *state.last_focus_state.lock().unwrap() = None;
-        *state.last_state_change.lock().unwrap() = None;
-    }
+// Kill processes running our backend script from the backend directory, other
+// than `managed` (the child we launched); returns the PIDs killed
+fn kill_stale_python_backends(app_handle: Option<&tauri::AppHandle>, managed: Option<u32>) -> Result<Vec<u32>, String> {
+    let script = env_or(PYTHON_BACKEND_SCRIPT_ENV, DEFAULT_PYTHON_BACKEND_SCRIPT);
+    let python_dir = resolve_python_dir(app_handle, &script).map_err(|e| e.to_string())?;
+    let script_path = python_dir.join(&script);
+    let own_pid = std::process::id();
 
-    // Send disconnection message only once
-    if !*connected && !*last_message_sent {
-        let disconn_msg = DuckMessage {
-            message: "EEG Disconnected - Please connect your Muse headset".to_string(),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            msg_type: "connection_status".to_string(),
-            focus_state: None,
-            metrics: None,
-        };
+    let mut system = sysinfo::System::new();
+    system.refresh_processes();
 
-        if let Some(app) = state.tauri_handle.lock().unwrap().as_ref() {
-            let _ = app.emit("duck-message", disconn_msg.clone());
+    let mut killed = Vec::new();
+    for (pid, process) in system.processes() {
+        let pid = pid.as_u32();
+        if pid == own_pid || Some(pid) == managed {
+            continue;
+        }
+        let in_backend_dir = process.cwd().is_some_and(|cwd| cwd == python_dir);
+        let runs_script = process.cmd().iter().skip(1).any(|arg| {
+            let arg = std::path::Path::new(arg);
+            arg == script_path || (in_backend_dir && arg == std::path::Path::new(&script))
+        });
+        if !runs_script {
+            continue;
+        }
+        if process.kill() {
+            warn!("🔪 Killed stale Python backend (PID {})", pid);
+            killed.push(pid);
+        } else {
+            warn!("⚠️ Failed to kill stale Python backend (PID {})", pid);
         }
-        let _ = state.ws_tx.send(disconn_msg);
-
-        *last_message_sent = true;
     }
+    Ok(killed)
 }
 
 // Launch Python backend subprocess
-fn launch_python_backend() -> Result<Child, std::io::Error> {
-    println!("🐍 Launching Python backend...");
+fn launch_python_backend(app_handle: Option<&tauri::AppHandle>, server_port: u16) -> Result<Child, io::Error> {
+    info!("🐍 Launching Python backend...");
 
-    // Find python executable
-    let python_cmd = if cfg!(target_os = "windows") {
-        "python"
-    } else {
-        "python"
-    };
+    let python_cmd = env_or(PYTHON_BIN_ENV, DEFAULT_PYTHON_BIN);
+    let script = env_or(PYTHON_BACKEND_SCRIPT_ENV, DEFAULT_PYTHON_BACKEND_SCRIPT);
 
     // Get the path to python-backend directory
-    let python_dir = std::env::current_dir()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("python-backend");
+    let python_dir = resolve_python_dir(app_handle, &script)?;
 
-    println!("📁 Python directory: {}", python_dir.display());
+    info!("📁 Python directory: {}", python_dir.display());
 
     // Launch Python process
-    let child = Command::new(python_cmd)
-        .arg("main.py")
+    let child = Command::new(&python_cmd)
+        .arg(&script)
         .current_dir(&python_dir)
-        .spawn()?;
+        .env("PYTHONUNBUFFERED", "1")
+        .env("DUCK_SERVER_PORT", server_port.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run {} {}: {}", python_cmd, script, e)))?;
 
-    println!("✅ Python backend started (PID: {})", child.id());
+    info!("✅ Python backend started (PID: {})", child.id());
     Ok(child)
 }
 
+// Broadcast channel capacity from DUCK_WS_CHANNEL_CAPACITY, raised to the minimum
+// when too small; unset or unparsable values use the default
+fn ws_channel_capacity_from_env() -> usize {
+    let Ok(value) = std::env::var(WS_CHANNEL_CAPACITY_ENV) else {
+        return DEFAULT_WS_CHANNEL_CAPACITY;
+    };
+    match value.trim().parse::<usize>() {
+        Ok(capacity) if capacity >= MIN_WS_CHANNEL_CAPACITY => capacity,
+        Ok(capacity) => {
+            warn!(
+                "⚠️ {}={} is below the minimum of {}; using {}",
+                WS_CHANNEL_CAPACITY_ENV, capacity, MIN_WS_CHANNEL_CAPACITY, MIN_WS_CHANNEL_CAPACITY
+            );
+            MIN_WS_CHANNEL_CAPACITY
+        }
+        Err(e) => {
+            warn!(
+                "⚠️ Invalid {}={:?}: {}; using {}",
+                WS_CHANNEL_CAPACITY_ENV, value, e, DEFAULT_WS_CHANNEL_CAPACITY
+            );
+            DEFAULT_WS_CHANNEL_CAPACITY
+        }
+    }
+}
+
+// Interface to bind from SERVER_HOST, defaulting to loopback
+fn server_host_from_env() -> Result<IpAddr, String> {
+    let Ok(value) = std::env::var(SERVER_HOST_ENV) else {
+        return Ok(DEFAULT_SERVER_HOST);
+    };
+    let value = value.trim();
+    let host = if value.eq_ignore_ascii_case("localhost") {
+        DEFAULT_SERVER_HOST
+    } else {
+        value.parse::<IpAddr>().map_err(|e| {
+            format!(
+                "Invalid {}={:?}: {} (expected an IP address such as 127.0.0.1 or 0.0.0.0)",
+                SERVER_HOST_ENV, value, e
+            )
+        })?
+    };
+
+    if !host.is_loopback() {
+        warn!(
+            "⚠️ Binding to {} exposes the API beyond this machine; set {} to require a token on /api/message",
+            host, API_TOKEN_ENV
+        );
+    }
+    Ok(host)
+}
+
+// Address to reach our own server at; a wildcard bind is reached through loopback
+fn reachable_host(host: IpAddr) -> IpAddr {
+    if host.is_unspecified() {
+        DEFAULT_SERVER_HOST
+    } else {
+        host
+    }
+}
+
+// Bind the first free port in HTTP_SERVER_PORTS on the configured host
+async fn bind_http_listener() -> Result<(tokio::net::TcpListener, SocketAddr), String> {
+    let host = server_host_from_env()?;
+    for port in HTTP_SERVER_PORTS {
+        let addr = SocketAddr::new(host, port);
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => return Ok((listener, addr)),
+            Err(e) => warn!("⚠️ {} unavailable: {}", addr, e),
+        }
+    }
+    Err(format!(
+        "No free port for the HTTP server in {}-{}; close whatever is using them and restart",
+        HTTP_SERVER_PORTS.start(),
+        HTTP_SERVER_PORTS.end()
+    ))
+}
+
 // Start HTTP + WebSocket server
-async fn start_servers(app_handle: tauri::AppHandle) {
-    let (tx, _rx) = broadcast::channel::<DuckMessage>(100);
+async fn start_servers(app_handle: tauri::AppHandle, listener: tokio::net::TcpListener, addr: SocketAddr) {
+    let state = init_state(Some(app_handle.clone()), addr);
+
+    // Make state available to Tauri commands
+    app_handle.manage(state.clone());
+
+    serve(state, listener).await;
+}
+
+// Launch the Python backend, open storage and start the monitor. Without an
+// app handle (headless mode) messages only go to WebSocket clients.
+fn init_state(app_handle: Option<tauri::AppHandle>, server_addr: SocketAddr) -> AppState {
+    let server_port = server_addr.port();
+    let ws_capacity = ws_channel_capacity_from_env();
+    info!("📡 WebSocket broadcast channel capacity: {} messages", ws_capacity);
+
+    if env_flag(KILL_STALE_BACKENDS_ENV) {
+        if let Err(e) = kill_stale_python_backends(app_handle.as_ref(), None) {
+            warn!("⚠️ Could not check for stale Python backends: {}", e);
+        }
+    }
 
     // Launch Python backend as subprocess
-    let python_process = match launch_python_backend() {
-        Ok(child) => {
-            println!("✅ Python subprocess launched successfully");
-            Some(child)
+    let python_process = if skip_python_launch() {
+        info!("⏭️ {} set - not launching the Python backend; expecting one to be run externally", SKIP_PYTHON_LAUNCH_ENV);
+        None
+    } else {
+        match launch_python_backend(app_handle.as_ref(), server_port) {
+            Ok(child) => {
+                info!("✅ Python subprocess launched successfully");
+                Some(child)
+            }
+            Err(e) => {
+                error!("❌ Failed to launch Python backend: {}", e);
+                warn!("⚠️  Make sure python-backend/main.py exists");
+                warn!("⚠️  Continuing in external-backend mode: run python-backend/main.py manually");
+                None
+            }
+        }
+    };
+
+    let data_paths = match &app_handle {
+        Some(handle) => resolve_data_paths(handle),
+        None => headless_data_paths(),
+    };
+
+    // Restore the last working Muse port unless disabled in the config file
+    let config_path = data_paths.as_ref().ok().map(|paths| paths.config_file.clone());
+    let saved_config = config_path.as_deref().map(config::read_config).unwrap_or_default();
+    let remember_muse_port = saved_config
+        .get("remember_muse_port")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let last_muse_port = saved_config
+        .get("last_muse_port")
+        .and_then(|v| v.as_u64())
+        .and_then(|port| u16::try_from(port).ok())
+        .filter(|_| remember_muse_port);
+    if let Some(port) = last_muse_port {
+        info!("💾 Last Muse port: {}", port);
+    }
+    let tunables = match config_path.as_deref().map(config::load_config) {
+        Some(Ok(loaded)) => loaded,
+        Some(Err(e)) => {
+            warn!("⚠️ {}; using default settings", e);
+            config::Config::default()
         }
+        None => config::Config::default(),
+    };
+
+    // Open the history database
+    let db = match &data_paths {
+        Ok(paths) => match std::fs::create_dir_all(&paths.data_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| storage::open_db(&paths.db_path).map_err(|e| e.to_string()))
+        {
+            Ok(conn) => {
+                info!("💾 History database: {}", paths.db_path.display());
+                Some(conn)
+            }
+            Err(e) => {
+                warn!("⚠️ Failed to open history database: {}", e);
+                None
+            }
+        },
         Err(e) => {
-            eprintln!("❌ Failed to launch Python backend: {}", e);
-            eprintln!("⚠️  Make sure python-backend/main.py exists");
-            eprintln!("⚠️  You can still run Python manually if needed");
+            warn!("⚠️ {}", e);
             None
         }
     };
 
-    let state = AppState {
-        ws_tx: tx,
-        message_count: Arc::new(Mutex::new(0)),
-        tauri_handle: Arc::new(Mutex::new(Some(app_handle.clone()))),
-        python_process: Arc::new(Mutex::new(python_process)),
-        last_focus_state: Arc::new(Mutex::new(None)),
-        last_state_change: Arc::new(Mutex::new(None)),
-        muse_connected: Arc::new(Mutex::new(false)),
-        consecutive_failures: Arc::new(Mutex::new(0)),
-    };
+    let state = build_state(
+        server_addr,
+        StateSetup {
+            tauri_handle: app_handle,
+            python_process,
+            db,
+            config_path,
+            remember_muse_port,
+            last_muse_port,
+            muse_ports: muse_ports_from_config(&tunables),
+            forced_muse_port: forced_muse_port_from_env(),
+            syslog: syslog::SyslogSink::from_env().map(Arc::new),
+            api_token: api_token_from_env().map(Arc::new),
+            ws_capacity,
+        },
+    );
+
+    apply_config(&state, &tunables);
+    capture_backend_output(&state);
+
+    // Relaunch the backend if it dies; an externally run backend is left alone
+    if lock_or_recover(&state.python_process).is_some() {
+        tokio::spawn(supervise_python_backend(state.clone()));
+    }
 
     // Start Muse monitoring task
     let monitor_state = state.clone();
     tokio::spawn(async move {
         monitor_muse_metrics(monitor_state).await;
     });
+    tokio::spawn(run_heartbeat(state.clone()));
 
-    // Make state available to Tauri commands
-    app_handle.manage(state.clone());
+    state
+}
+
+// What init_state gathers from the environment, config file and Python launch
+struct StateSetup {
+    tauri_handle: Option<tauri::AppHandle>,
+    python_process: Option<Child>,
+    db: Option<rusqlite::Connection>,
+    config_path: Option<PathBuf>,
+    remember_muse_port: bool,
+    last_muse_port: Option<u16>,
+    muse_ports: Vec<u16>,
+    forced_muse_port: Option<u16>,
+    syslog: Option<Arc<syslog::SyslogSink>>,
+    api_token: Option<Arc<String>>,
+    ws_capacity: usize,
+}
+
+// No window, backend, storage or token; what tests start from
+impl Default for StateSetup {
+    fn default() -> Self {
+        StateSetup {
+            tauri_handle: None,
+            python_process: None,
+            db: None,
+            config_path: None,
+            remember_muse_port: false,
+            last_muse_port: None,
+            muse_ports: DEFAULT_MUSE_API_PORTS.to_vec(),
+            forced_muse_port: None,
+            syslog: None,
+            api_token: None,
+            ws_capacity: DEFAULT_WS_CHANNEL_CAPACITY,
+        }
+    }
+}
+
+// Fresh state with every setting at its default; starts no tasks
+fn build_state(server_addr: SocketAddr, setup: StateSetup) -> AppState {
+    let StateSetup {
+        tauri_handle,
+        python_process,
+        db,
+        config_path,
+        remember_muse_port,
+        last_muse_port,
+        muse_ports,
+        forced_muse_port,
+        syslog,
+        api_token,
+        ws_capacity,
+    } = setup;
+    let (tx, _rx) = broadcast::channel::<DuckMessage>(ws_capacity);
+    let (log_tx, _log_rx) = broadcast::channel::<LogLine>(100);
+
+    AppState {
+        ws_tx: tx,
+        message_count: Arc::new(Mutex::new(0)),
+        next_seq: Arc::new(AtomicU64::new(1)),
+        broadcast_order: Arc::new(Mutex::new(())),
+        message_times: Arc::new(Mutex::new(VecDeque::new())),
+        tauri_handle: Arc::new(Mutex::new(tauri_handle)),
+        python_process: Arc::new(Mutex::new(python_process)),
+        backend_restarts: Arc::new(Mutex::new(0)),
+        last_focus_state: Arc::new(Mutex::new(None)),
+        last_state_change: Arc::new(Mutex::new(None)),
+        muse_connected: Arc::new(Mutex::new(false)),
+        consecutive_failures: Arc::new(Mutex::new(0)),
+        parse_error_count: Arc::new(Mutex::new(0)),
+        annotations: Arc::new(Mutex::new(VecDeque::new())),
+        focus_transitions: Arc::new(Mutex::new(VecDeque::new())),
+        metrics_history: Arc::new(Mutex::new(VecDeque::new())),
+        log_lines: Arc::new(Mutex::new(VecDeque::new())),
+        log_tx,
+        log_stream_task: Arc::new(Mutex::new(None)),
+        forward_backend_logs: Arc::new(Mutex::new(false)),
+        vote_config: Arc::new(Mutex::new(VoteConfig::default())),
+        classification_window: Arc::new(Mutex::new(VecDeque::new())),
+        classification_mode: Arc::new(Mutex::new(ClassificationMode::default())),
+        focus_hysteresis: Arc::new(Mutex::new(FocusHysteresis::default())),
+        unfocused_keywords: Arc::new(Mutex::new(default_unfocused_keywords())),
+        fullscreen_active: Arc::new(Mutex::new(false)),
+        suppression_notified: Arc::new(Mutex::new(false)),
+        db: Arc::new(Mutex::new(db)),
+        loop_stats: Arc::new(Mutex::new(LoopStats::default())),
+        fault_config: Arc::new(Mutex::new(FaultConfig::default())),
+        muse_schema: Arc::new(Mutex::new(MuseSchema::default())),
+        detected_schema: Arc::new(Mutex::new(None)),
+        connection_uptime: Arc::new(Mutex::new(ConnectionUptime::default())),
+        transition_log_path: Arc::new(Mutex::new(None)),
+        metrics_format: Arc::new(Mutex::new(MetricsFormat::default())),
+        simulation_active: Arc::new(Mutex::new(false)),
+        config_path,
+        remember_muse_port,
+        last_muse_port: Arc::new(Mutex::new(last_muse_port)),
+        last_processed_score: Arc::new(Mutex::new(None)),
+        change_start_score: Arc::new(Mutex::new(None)),
+        max_metrics_age_secs: Arc::new(Mutex::new(None)),
+        clock_offset_secs: Arc::new(Mutex::new(None)),
+        metrics_stale: Arc::new(Mutex::new(false)),
+        smoothed_score: Arc::new(Mutex::new(None)),
+        score_smoothing_alpha: Arc::new(Mutex::new(DEFAULT_SCORE_SMOOTHING_ALPHA)),
+        score_alerts: Arc::new(Mutex::new(Vec::new())),
+        next_alert_id: Arc::new(Mutex::new(0)),
+        recording: Arc::new(Mutex::new(None)),
+        port_selection: Arc::new(Mutex::new(PortSelection::default())),
+        draining: Arc::new(AtomicBool::new(false)),
+        state_cooldowns: Arc::new(Mutex::new(StateCooldowns::default())),
+        last_nudge_at: Arc::new(Mutex::new(HashMap::new())),
+        stability_window: Arc::new(Mutex::new(DEFAULT_STABILITY_WINDOW)),
+        poll_interval: Arc::new(Mutex::new(std::time::Duration::from_millis(POLL_INTERVAL_MS))),
+        false_positives: Arc::new(Mutex::new(Vec::new())),
+        http_client: reqwest::Client::new(),
+        active_muse_port: Arc::new(Mutex::new(None)),
+        port_probes: Arc::new(Mutex::new(HashMap::new())),
+        max_reconnect_attempts: Arc::new(Mutex::new(None)),
+        reconnect_attempts: Arc::new(Mutex::new(0)),
+        reconnect_gave_up: Arc::new(Mutex::new(false)),
+        disconnect_threshold: Arc::new(Mutex::new(DEFAULT_DISCONNECT_THRESHOLD)),
+        message_priorities: Arc::new(Mutex::new(default_message_priorities())),
+        syslog,
+        pending_alerts: Arc::new(Mutex::new(VecDeque::new())),
+        next_pending_alert_id: Arc::new(Mutex::new(0)),
+        alert_ttl_secs: Arc::new(Mutex::new(DEFAULT_ALERT_TTL_SECS)),
+        look_away_since: Arc::new(Mutex::new(None)),
+        look_away_notified: Arc::new(Mutex::new(false)),
+        alert_merge_window_ms: Arc::new(Mutex::new(DEFAULT_ALERT_MERGE_WINDOW_MS)),
+        pending_nudge: Arc::new(Mutex::new(None)),
+        session_id: Arc::new(Mutex::new(new_session_id())),
+        session_tag: Arc::new(Mutex::new(None)),
+        focus_session: Arc::new(Mutex::new(None)),
+        last_focus_session: Arc::new(Mutex::new(None)),
+        unacked_distractions: Arc::new(Mutex::new(VecDeque::new())),
+        display_connection: Arc::new(Mutex::new(DisplayConnection {
+            state: "connecting",
+            since: std::time::Instant::now(),
+        })),
+        connection_display_min_secs: Arc::new(Mutex::new(DEFAULT_CONNECTION_DISPLAY_MIN_SECS)),
+        connection_status_gate: Arc::new(Mutex::new(ConnectionStatusGate::default())),
+        connection_status_debounce: Arc::new(Mutex::new(std::time::Duration::from_secs_f64(
+            DEFAULT_CONNECTION_STATUS_DEBOUNCE_SECS,
+        ))),
+        heartbeat_interval_secs: Arc::new(Mutex::new(DEFAULT_HEARTBEAT_INTERVAL_SECS)),
+        emit_retry_config: Arc::new(Mutex::new(EmitRetryConfig::default())),
+        emit_retry: Arc::new(Mutex::new(EmitRetryState::default())),
+        frontend_emit: Arc::new(Mutex::new(FrontendEmitHealth::default())),
+        muse_ports: Arc::new(muse_ports),
+        forced_muse_port,
+        latest_metrics: Arc::new(Mutex::new(None)),
+        heart_rate_thresholds: Arc::new(Mutex::new(HeartRateThresholds::default())),
+        movement_alert: Arc::new(Mutex::new(MovementAlertConfig::default())),
+        fidget_episode: Arc::new(Mutex::new(None)),
+        heart_rate_zone: Arc::new(Mutex::new(HeartRateZone::Normal)),
+        ws_clients: Arc::new(Mutex::new(HashMap::new())),
+        shutdown: Arc::new(watch::channel(false).0),
+        server_port: server_addr.port(),
+        server_host: server_addr.ip(),
+        started_at: std::time::Instant::now(),
+        started_at_utc: chrono::Utc::now(),
+        api_token,
+    }
+}
 
+// Serve the HTTP API and WebSocket until shutdown
+async fn serve(state: AppState, listener: tokio::net::TcpListener) {
     // Build Axum router
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/api/message", post(receive_message))
+        .route("/api/message/:client_id", post(receive_client_message))
         .route("/api/video", post(receive_video))
+        .route("/api/annotation", post(receive_annotation))
+        .route("/api/drain", post(drain_server).delete(undrain_server))
+        .route("/api/metrics", get(latest_metrics_endpoint))
+        .route("/api/history", get(metrics_history_endpoint))
+        .route("/api/status", get(status_endpoint))
+        .route("/metrics", get(prometheus_metrics_endpoint))
         .route("/ws", get(websocket_handler))
         .layer(
             CorsLayer::new()
@@ -534,48 +5687,522 @@ async fn start_servers(app_handle: tauri::AppHandle) {
                 .allow_methods(Any)
                 .allow_headers(Any),
         )
-        .with_state(state);
-
-    // Start HTTP server on port 3030
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3030")
-        .await
-        .expect("Failed to bind to port 3030");
+        .with_state(state.clone());
 
-    println!("🚀 HTTP Server started on http://127.0.0.1:3030");
-    println!("🔌 WebSocket Server started on ws://127.0.0.1:3030/ws");
+    let server_addr = SocketAddr::new(state.server_host, state.server_port);
+    info!("🚀 HTTP Server started on http://{}", server_addr);
+    info!("🔌 WebSocket Server started on ws://{}/ws", server_addr);
 
     axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(state))
         .await
         .expect("Failed to start server");
+    info!("✅ HTTP server stopped");
+}
+
+// Route tracing output to stderr, filtered by RUST_LOG (default: info)
+fn init_logging() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let _ = tracing_subscriber::fmt().with_env_filter(filter).try_init();
+}
+
+// Run the monitor and HTTP/WebSocket server without a window, e.g. on a
+// Raspberry Pi feeding a separate display. Stops on Ctrl+C.
+pub fn run_headless() {
+    init_logging();
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start tokio runtime");
+    runtime.block_on(async {
+        info!("🖥️ Running headless (no Tauri window)");
+        #[cfg(feature = "mock")]
+        let _mock = match std::env::var_os(MOCK_MUSE_ENV) {
+            Some(_) => match start_mock_muse(MockStep::demo_script()).await {
+                Ok(mock) => Some(mock),
+                Err(e) => {
+                    error!("❌ Failed to start mock Muse bridge: {}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+        let (listener, addr) = match bind_http_listener().await {
+            Ok(bound) => bound,
+            Err(e) => {
+                error!("❌ {}", e);
+                return;
+            }
+        };
+        let state = init_state(None, addr);
+        let mut server = tokio::spawn(serve(state.clone(), listener));
+
+        tokio::select! {
+            _ = termination_signal() => {
+                request_shutdown(&state);
+                let _ = server.await;
+            }
+            _ = &mut server => {}
+        }
+
+        shutdown_app(&state);
+    });
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    init_logging();
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
             let app_handle = app.handle().clone();
 
+            // Bind up front so a port problem fails setup with a readable error
+            let (listener, addr) = tauri::async_runtime::block_on(bind_http_listener())?;
+
             // Start HTTP + WebSocket servers in background
+            let signal_handle = app_handle.clone();
             tauri::async_runtime::spawn(async move {
-                start_servers(app_handle).await;
+                start_servers(app_handle, listener, addr).await;
+            });
+
+            // Killed from a terminal: clean up like a window close, then exit
+            tauri::async_runtime::spawn(async move {
+                termination_signal().await;
+                info!("🛑 Termination signal received");
+                if let Some(state) = signal_handle.try_state::<AppState>() {
+                    shutdown_app(&state);
+                }
+                signal_handle.exit(0);
             });
 
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            if matches!(payload.event(), tauri::webview::PageLoadEvent::Finished) {
+                if let Some(state) = webview.app_handle().try_state::<AppState>() {
+                    resume_frontend_emits(&state);
+                }
+            }
+        })
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { .. } = event {
                 // Clean up Python process on exit
                 if let Some(state) = window.app_handle().try_state::<AppState>() {
-                    if let Some(mut child) = state.python_process.lock().unwrap().take() {
-                        println!("🛑 Shutting down Python backend...");
-                        let _ = child.kill();
-                        println!("✅ Python backend stopped");
-                    }
+                    shutdown_app(&state);
                 }
             }
         })
-        .invoke_handler(tauri::generate_handler![greet, get_service_status])
+        .invoke_handler(tauri::generate_handler![
+            greet, get_service_status, get_annotations, get_hr_focus_correlation,
+            get_backend_logs, start_log_stream, stop_log_stream, set_vote_config,
+            get_vote_status, get_data_paths, set_fullscreen_active,
+            get_weekly_digest, get_loop_timing, set_fault_injection,
+            set_muse_schema, get_connection_ratio, reset_session,
+            set_transition_log, run_diagnostics, set_metrics_format, get_formatted_metrics,
+            get_client_count, simulate_decline, get_significant_transitions,
+            get_avg_refocus_time, get_focus_percent,
+            set_max_metrics_age, set_score_alert, get_score_alerts, cancel_score_alert,
+            start_recording, stop_recording, diagnose_headset, set_port_selection,
+            compare_to_yesterday, set_state_cooldowns, get_state_cooldowns,
+            get_full_samples, flag_false_positive, auto_tune_thresholds,
+            send_python_command, get_optimal_focus_times, set_max_reconnect_attempts,
+            reset_connection, get_timeline_segments, set_message_priority,
+            get_message_priorities, benchmark_pipeline, get_pending_alerts, acknowledge_alert,
+            set_alert_ttl, set_alert_merge_window, set_session_tag, get_stats_by_tag,
+            get_display_connection_state, set_connection_display_min_secs,
+            get_focus_velocity, set_emit_retry, get_emit_retry_stats, list_buffers,
+            clear_buffer, get_latest_metrics, set_stability_window, set_heart_rate_thresholds,
+            get_server_port, set_disconnect_threshold, start_session, stop_session,
+            set_movement_alert, export_session_csv, set_backend_log_forwarding, set_poll_interval,
+            set_classification_mode, get_classification_mode, set_connection_status_debounce,
+            set_heartbeat_interval, set_smoothing, reload_config, get_distraction_rate,
+            set_focus_thresholds, get_current_streak, acknowledge_distraction, set_unfocused_keywords,
+            get_unfocused_keywords, simulate_focus_state, get_discovery_report, kill_stale_backends
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_state() -> AppState {
+        build_state(SocketAddr::from(([127, 0, 0, 1], 0)), StateSetup::default())
+    }
+
+    #[test]
+    fn msgpack_frame_round_trips_to_the_same_message() {
+        let msg = DuckMessage {
+            message: "Back on track".to_string(),
+            timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+            msg_type: "focus_state_change".to_string(),
+            focus_state: Some("focused".to_string()),
+            metrics: Some(MessageMetrics {
+                attention: "focused".to_string(),
+                focus_score: 0.8,
+                brain_state: "simulated".to_string(),
+            }),
+            priority: Some(PRIORITY_IMPORTANT),
+            event_id: Some("evt-1".to_string()),
+            seq: Some(42),
+            conditions: Some(vec!["distracted".to_string(), "look_away".to_string()]),
+        };
+
+        let Some(Message::Binary(bytes)) = encode_message(&msg, None, WsFormat::Msgpack) else {
+            panic!("msgpack should encode to a binary frame");
+        };
+        let decoded: DuckMessage = rmp_serde::from_slice(&bytes).expect("decodes as a DuckMessage");
+
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&msg).unwrap()
+        );
+    }
+
+    fn votes(labels: &[&str]) -> VecDeque<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn vote_ties_prefer_the_committed_state() {
+        let window = votes(&["focused", "distracted", "distracted", "focused"]);
+        assert_eq!(leading_label(&window, Some("focused")), Some(("focused".to_string(), 2)));
+        assert_eq!(leading_label(&window, Some("distracted")), Some(("distracted".to_string(), 2)));
+    }
+
+    #[test]
+    fn vote_ties_without_a_committed_leader_go_to_the_latest_vote() {
+        let window = votes(&["focused", "distracted", "distracted", "focused"]);
+        assert_eq!(leading_label(&window, None), Some(("focused".to_string(), 2)));
+        assert_eq!(leading_label(&window, Some("neutral")), Some(("focused".to_string(), 2)));
+
+        let mut window = VecDeque::new();
+        let config = VoteConfig { window: 4, required: 2 };
+        for label in ["focused", "focused", "distracted"] {
+            vote_classification(&mut window, config, label, None);
+        }
+        assert_eq!(
+            vote_classification(&mut window, config, "distracted", None),
+            Some("distracted".to_string())
+        );
+    }
+
+    fn distraction(event_id: &str) -> DuckMessage {
+        DuckMessage {
+            focus_state: Some("unfocused".to_string()),
+            event_id: Some(event_id.to_string()),
+            ..DuckMessage::new("focus_state_change", "Time to refocus!")
+        }
+    }
+
+    #[test]
+    fn distraction_is_sent_at_once_and_starts_the_ack_clock() {
+        let state = test_state();
+        let mut rx = state.ws_tx.subscribe();
+
+        dispatch_focus_change(&state, distraction("evt-1"), &synthetic_metrics(0.2));
+
+        let sent = rx.try_recv().expect("sent without waiting for the merge window");
+        assert_eq!(sent.msg_type, "focus_state_change");
+        assert_eq!(sent.conditions, None);
+        assert!(lock_or_recover(&state.pending_nudge).is_none());
+        let unacked = lock_or_recover(&state.unacked_distractions);
+        assert_eq!(unacked.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(), ["evt-1"]);
+    }
+
+    #[test]
+    fn distraction_absorbs_a_held_alert_and_keeps_its_type() {
+        let state = test_state();
+        let mut rx = state.ws_tx.subscribe();
+        let mut look_away = distraction("unused");
+        look_away.msg_type = "look_away".to_string();
+        look_away.focus_state = None;
+        look_away.event_id = None;
+        raise_alert(&state, "look_away", look_away);
+        assert!(rx.try_recv().is_err(), "look_away is held for the merge window");
+
+        dispatch_focus_change(&state, distraction("evt-2"), &synthetic_metrics(0.2));
+
+        let sent = rx.try_recv().expect("sent without waiting for the merge window");
+        assert_eq!(sent.msg_type, "focus_state_change");
+        assert_eq!(sent.event_id.as_deref(), Some("evt-2"));
+        assert_eq!(sent.conditions, Some(vec!["distracted".to_string(), "look_away".to_string()]));
+        assert!(lock_or_recover(&state.pending_nudge).is_none());
+        assert!(rx.try_recv().is_err());
+    }
+
+    fn keywords(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn custom_keyword_maps_to_unfocused() {
+        let custom = keywords(&["drowsy"]);
+        assert_eq!(focus_state_for("drowsy", &custom), "unfocused");
+        assert_eq!(focus_state_for("  Very Drowsy ", &custom), "unfocused");
+        // A keyword can also pull in a label that is otherwise focused
+        assert_eq!(focus_state_for("neutral", &keywords(&["neutral"])), "unfocused");
+    }
+
+    #[test]
+    fn keywords_match_whole_words_only() {
+        let custom = keywords(&["focus", "mind wandering"]);
+        assert_eq!(focus_state_for("focused", &custom), "focused");
+        assert_eq!(focus_state_for("lost_focus", &custom), "unfocused");
+        assert_eq!(focus_state_for("mind-wandering", &custom), "unfocused");
+        assert_eq!(focus_state_for("neutral", &custom), "focused");
+    }
+
+    #[test]
+    fn unknown_labels_default_to_unfocused() {
+        let defaults = default_unfocused_keywords();
+        assert_eq!(focus_state_for("neutral", &defaults), "focused");
+        assert_eq!(focus_state_for("Focused", &defaults), "focused");
+        assert_eq!(focus_state_for("daydreaming", &defaults), "unfocused");
+        assert_eq!(focus_state_for("unknown", &defaults), "unfocused");
+        assert_eq!(focus_state_for("unfocused", &defaults), "unfocused");
+    }
+
+    #[test]
+    fn clamp_leaves_normal_readings_alone() {
+        let mut metrics = synthetic_metrics(0.7);
+        assert!(metrics.validate_and_clamp(None).is_empty());
+        assert_eq!(metrics.focus_score, 0.7);
+        assert_eq!(metrics.heart_rate, 70.0);
+    }
+
+    #[test]
+    fn clamp_replaces_nan_with_the_previous_reading() {
+        let previous = synthetic_metrics(0.4);
+        let mut metrics = synthetic_metrics(0.7);
+        metrics.focus_score = f64::NAN;
+        metrics.movement_intensity = f64::INFINITY;
+
+        let notes = metrics.validate_and_clamp(Some(&previous));
+        assert_eq!(notes.len(), 2);
+        assert_eq!(metrics.focus_score, 0.4);
+        assert_eq!(metrics.movement_intensity, 0.0);
+
+        let mut first = synthetic_metrics(0.7);
+        first.focus_score = f64::NAN;
+        first.validate_and_clamp(None);
+        assert_eq!(first.focus_score, 0.0);
+    }
+
+    #[test]
+    fn clamp_pulls_out_of_range_values_in_and_drops_implausible_heart_rates() {
+        let mut metrics = synthetic_metrics(0.7);
+        metrics.focus_score = 1.5;
+        metrics.movement_intensity = -0.2;
+        metrics.heart_rate = 400.0;
+
+        let notes = metrics.validate_and_clamp(None);
+        assert_eq!(notes.len(), 3);
+        assert_eq!(metrics.focus_score, 1.0);
+        assert_eq!(metrics.movement_intensity, 0.0);
+        // Not clamped: an implausible rate is dropped to 0 (no reading)
+        assert_eq!(metrics.heart_rate, 0.0);
+    }
+
+    fn bearer(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn authorization_accepts_the_exact_token() {
+        assert!(is_authorized(Some("s3cret"), &bearer("Bearer s3cret")));
+    }
+
+    #[test]
+    fn authorization_rejects_wrong_or_missing_tokens() {
+        assert!(!is_authorized(Some("s3cret"), &HeaderMap::new()));
+        assert!(!is_authorized(Some("s3cret"), &bearer("Bearer s3cre")));
+        assert!(!is_authorized(Some("s3cret"), &bearer("Bearer s3cret ")));
+        assert!(!is_authorized(Some("s3cret"), &bearer("Bearer S3CRET")));
+        assert!(!is_authorized(Some("s3cret"), &bearer("Basic s3cret")));
+    }
+
+    #[test]
+    fn authorization_is_open_without_a_configured_token() {
+        assert!(is_authorized(None, &HeaderMap::new()));
+        assert!(is_authorized(None, &bearer("Bearer anything")));
+    }
+
+    fn focus_changes(rx: &mut broadcast::Receiver<DuckMessage>) -> Vec<DuckMessage> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|msg| msg.msg_type == "focus_state_change")
+            .collect()
+    }
+
+    #[test]
+    fn dwell_shorter_than_the_stability_window_sends_nothing() {
+        let state = test_state();
+        *lock_or_recover(&state.stability_window) = std::time::Duration::from_secs(2);
+        let mut rx = state.ws_tx.subscribe();
+        let start = std::time::Instant::now();
+        let secs = std::time::Duration::from_secs_f64;
+
+        for _ in 0..3 {
+            process_metrics(&state, &synthetic_metrics(0.8), start);
+        }
+        for _ in 0..3 {
+            process_metrics(&state, &synthetic_metrics(0.2), start + secs(1.0));
+        }
+        process_metrics(&state, &synthetic_metrics(0.2), start + secs(2.9));
+        assert!(focus_changes(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn dwell_past_the_stability_window_sends_exactly_once() {
+        let state = test_state();
+        *lock_or_recover(&state.stability_window) = std::time::Duration::from_secs(2);
+        let mut rx = state.ws_tx.subscribe();
+        let start = std::time::Instant::now();
+        let secs = std::time::Duration::from_secs_f64;
+
+        for _ in 0..3 {
+            process_metrics(&state, &synthetic_metrics(0.8), start);
+        }
+        for _ in 0..3 {
+            process_metrics(&state, &synthetic_metrics(0.8), start + secs(2.5));
+        }
+
+        let sent = focus_changes(&mut rx);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].focus_state.as_deref(), Some("focused"));
+    }
+
+    #[tokio::test]
+    async fn muse_disconnects_on_the_threshold_failure() {
+        let state = test_state();
+        *lock_or_recover(&state.muse_connected) = true;
+        *lock_or_recover(&state.disconnect_threshold) = 3;
+        let mut rx = state.ws_tx.subscribe();
+        let mut last_message_sent = false;
+
+        for _ in 0..2 {
+            handle_muse_failure(&state, &mut last_message_sent, "Connection error").await;
+        }
+        assert!(*lock_or_recover(&state.muse_connected));
+        assert!(rx.try_recv().is_err());
+
+        handle_muse_failure(&state, &mut last_message_sent, "Connection error").await;
+        assert!(!*lock_or_recover(&state.muse_connected));
+        let sent = rx.try_recv().expect("disconnect status sent");
+        assert_eq!(sent.msg_type, "connection_status");
+        assert!(last_message_sent);
+    }
+
+    #[test]
+    fn lock_or_recover_survives_a_poisoned_mutex() {
+        let shared = Arc::new(Mutex::new(1));
+        let poisoner = shared.clone();
+        let _ = std::thread::spawn(move || {
+            let mut value = poisoner.lock().unwrap();
+            *value = 2;
+            panic!("poison the mutex");
+        })
+        .join();
+        assert!(shared.is_poisoned());
+
+        *lock_or_recover(&shared) += 1;
+        assert_eq!(*lock_or_recover(&shared), 3);
+    }
+
+    #[test]
+    fn poll_interval_below_the_minimum_is_clamped_with_an_error() {
+        let (interval, result) = clamp_poll_interval(MIN_POLL_INTERVAL_MS - 1);
+        assert_eq!(interval, std::time::Duration::from_millis(MIN_POLL_INTERVAL_MS));
+        assert!(result.is_err());
+
+        let (interval, result) = clamp_poll_interval(MAX_POLL_INTERVAL_MS + 1);
+        assert_eq!(interval, std::time::Duration::from_millis(MAX_POLL_INTERVAL_MS));
+        assert!(result.is_err());
+
+        let (interval, result) = clamp_poll_interval(MIN_POLL_INTERVAL_MS);
+        assert_eq!(interval, std::time::Duration::from_millis(MIN_POLL_INTERVAL_MS));
+        assert!(result.is_ok());
+    }
+
+    fn status(connected: bool) -> DuckMessage {
+        DuckMessage::new("connection_status", if connected { "EEG Connected" } else { "EEG Disconnected" })
+    }
+
+    #[test]
+    fn connection_flaps_coalesce_into_the_final_state() {
+        let interval = std::time::Duration::from_secs(1);
+        let ms = std::time::Duration::from_millis;
+        let start = std::time::Instant::now();
+        let mut gate = ConnectionStatusGate::default();
+
+        assert!(gate.offer(true, status(true), start, interval).is_some());
+        assert!(gate.offer(false, status(false), start + ms(100), interval).is_none());
+        assert!(gate.offer(true, status(true), start + ms(200), interval).is_none());
+        assert!(gate.offer(false, status(false), start + ms(300), interval).is_none());
+        assert!(gate.flush(start + ms(500), interval).is_none());
+
+        let sent = gate.flush(start + ms(1100), interval).expect("final state sent");
+        assert_eq!(sent.message, "EEG Disconnected");
+        assert!(gate.flush(start + ms(2200), interval).is_none());
+    }
+
+    #[test]
+    fn connection_flap_back_to_the_sent_state_is_dropped() {
+        let interval = std::time::Duration::from_secs(1);
+        let ms = std::time::Duration::from_millis;
+        let start = std::time::Instant::now();
+        let mut gate = ConnectionStatusGate::default();
+
+        assert!(gate.offer(true, status(true), start, interval).is_some());
+        assert!(gate.offer(false, status(false), start + ms(100), interval).is_none());
+        assert!(gate.offer(true, status(true), start + ms(200), interval).is_none());
+        assert!(gate.flush(start + ms(1100), interval).is_none());
+    }
+
+    // Feed each score through label() and count changes of the resulting state
+    fn hysteresis_transitions(hysteresis: &FocusHysteresis, start: &'static str, scores: &[f64]) -> usize {
+        let mut current = start;
+        let mut transitions = 0;
+        for &score in scores {
+            let next = hysteresis.label(Some(current), score);
+            if next != current {
+                transitions += 1;
+                current = next;
+            }
+        }
+        transitions
+    }
+
+    #[test]
+    fn score_oscillating_inside_the_deadband_never_transitions() {
+        let hysteresis = FocusHysteresis::default();
+        let scores = [0.45, 0.55, 0.42, 0.58, 0.5, 0.41, 0.59];
+        assert_eq!(hysteresis_transitions(&hysteresis, "focused", &scores), 0);
+        assert_eq!(hysteresis_transitions(&hysteresis, "unfocused", &scores), 0);
+
+        // Leaving the band on either side does switch
+        assert_eq!(hysteresis_transitions(&hysteresis, "focused", &[0.5, 0.3, 0.5, 0.7]), 2);
+    }
+
+    #[test]
+    fn recording_writes_broadcasts_without_counting_as_a_client() {
+        let state = test_state();
+        let path = std::env::temp_dir().join(format!("duck-recording-{}.jsonl", uuid::Uuid::new_v4()));
+        *lock_or_recover(&state.recording) = Some(Recording {
+            path: path.clone(),
+            include_metrics: false,
+            writer: io::BufWriter::new(std::fs::File::create(&path).unwrap()),
+            lines: 0,
+        });
+        assert_eq!(state.ws_tx.receiver_count(), 0);
+
+        broadcast(&state, status(true));
+
+        let recording = lock_or_recover(&state.recording).take().unwrap();
+        assert_eq!(recording.lines, 1);
+        drop(recording);
+        let _ = std::fs::remove_file(path);
+    }
+}
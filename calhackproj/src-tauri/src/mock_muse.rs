@@ -0,0 +1,203 @@
+// Synthetic Muse bridge for tests and demos, built with the `mock` feature
+use axum::{extract::State, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::muse_ports_from_env;
+
+// One stretch of the script: this attention label and score for `secs` seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockStep {
+    pub attention: String,
+    pub focus_score: f64,
+    pub secs: f64,
+}
+
+impl MockStep {
+    pub fn new(attention: &str, focus_score: f64, secs: f64) -> MockStep {
+        MockStep {
+            attention: attention.to_string(),
+            focus_score,
+            secs,
+        }
+    }
+
+    // Focused, drifting off, then back on task
+    pub fn demo_script() -> Vec<MockStep> {
+        vec![
+            MockStep::new("focused", 0.8, 10.0),
+            MockStep::new("distracted", 0.2, 10.0),
+            MockStep::new("focused", 0.75, 10.0),
+        ]
+    }
+}
+
+// A running mock bridge; the server stops when this is dropped
+pub struct MockMuse {
+    pub port: u16,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for MockMuse {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Script {
+    steps: Vec<MockStep>,
+    started: std::time::Instant,
+}
+
+impl Script {
+    // Step in effect now; the script loops once it runs out
+    fn current(&self) -> &MockStep {
+        let total: f64 = self.steps.iter().map(|step| step.secs).sum();
+        let mut at = self.started.elapsed().as_secs_f64();
+        if total > 0.0 {
+            at %= total;
+        }
+        self.steps
+            .iter()
+            .find(|step| {
+                at -= step.secs;
+                at < 0.0
+            })
+            .unwrap_or(&self.steps[self.steps.len() - 1])
+    }
+}
+
+// Serve `script` at /api/metrics on the first free port the monitor probes
+pub async fn start_mock_muse(script: Vec<MockStep>) -> std::io::Result<MockMuse> {
+    if script.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "mock script is empty"));
+    }
+
+    let mut bound = None;
+    for port in muse_ports_from_env() {
+        if let Ok(listener) = tokio::net::TcpListener::bind(("127.0.0.1", port)).await {
+            bound = Some((listener, port));
+            break;
+        }
+    }
+    let Some((listener, port)) = bound else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            "no free Muse API port for the mock bridge",
+        ));
+    };
+
+    let script = Arc::new(Script {
+        steps: script,
+        started: std::time::Instant::now(),
+    });
+    let app = Router::new()
+        .route("/api/metrics", get(mock_metrics))
+        .with_state(script);
+    let task = tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!("⚠️ Mock Muse bridge stopped: {}", e);
+        }
+    });
+
+    info!("🧪 Mock Muse bridge on port {}", port);
+    Ok(MockMuse { port, task })
+}
+
+// Same shape as the real bridge's response (standard schema)
+async fn mock_metrics(State(script): State<Arc<Script>>) -> Json<serde_json::Value> {
+    let step = script.current();
+    Json(serde_json::json!({
+        "attention": step.attention,
+        "focus_score": step.focus_score,
+        "brain_state": "mock",
+        "head_orientation": "center",
+        "heart_rate": 70.0,
+        "movement_intensity": 0.1,
+        "theta_beta_ratio": 2.5 - 1.5 * step.focus_score,
+        "timestamp": chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+    }))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{
+        build_state, lock_or_recover, monitor_muse_metrics, request_shutdown, AppState,
+        DuckMessage, StateSetup,
+    };
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    // Short demo script so a full focused/distracted/focused cycle fits in a test
+    fn short_script() -> Vec<MockStep> {
+        vec![
+            MockStep::new("focused", 0.8, 1.5),
+            MockStep::new("distracted", 0.2, 1.5),
+            MockStep::new("focused", 0.75, 3.0),
+        ]
+    }
+
+    async fn monitored_mock() -> (MockMuse, AppState, broadcast::Receiver<DuckMessage>) {
+        let mock = start_mock_muse(short_script()).await.expect("mock bridge");
+        let state = build_state(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            StateSetup {
+                muse_ports: vec![mock.port],
+                ..Default::default()
+            },
+        );
+        *lock_or_recover(&state.poll_interval) = Duration::from_millis(100);
+        *lock_or_recover(&state.stability_window) = Duration::from_millis(300);
+        let rx = state.ws_tx.subscribe();
+        tokio::spawn(monitor_muse_metrics(state.clone()));
+        (mock, state, rx)
+    }
+
+    // Next connection_status or focus_state_change, skipping everything else
+    async fn next_event(rx: &mut broadcast::Receiver<DuckMessage>) -> DuckMessage {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let msg = rx.recv().await.expect("ws channel open");
+                if msg.msg_type == "connection_status" || msg.msg_type == "focus_state_change" {
+                    return msg;
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for a monitor event")
+    }
+
+    #[tokio::test]
+    async fn reports_connection_before_first_focus_state() {
+        let (_mock, state, mut rx) = monitored_mock().await;
+
+        let first = next_event(&mut rx).await;
+        assert_eq!(first.msg_type, "connection_status");
+        assert_eq!(first.message, "EEG Connected");
+
+        let second = next_event(&mut rx).await;
+        assert_eq!(second.msg_type, "focus_state_change");
+        assert_eq!(second.focus_state.as_deref(), Some("focused"));
+
+        request_shutdown(&state);
+    }
+
+    #[tokio::test]
+    async fn follows_focused_distracted_focused_script() {
+        let (_mock, state, mut rx) = monitored_mock().await;
+
+        let mut seen = Vec::new();
+        while seen.len() < 3 {
+            let msg = next_event(&mut rx).await;
+            if msg.msg_type == "focus_state_change" {
+                seen.push(msg.focus_state.unwrap_or_default());
+            }
+        }
+        assert_eq!(seen, ["focused", "unfocused", "focused"]);
+
+        request_shutdown(&state);
+    }
+}
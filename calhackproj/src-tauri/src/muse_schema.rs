@@ -0,0 +1,114 @@
+// Field-name mappings for the JSON returned by different Muse bridge versions
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::MuseMetrics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MuseSchema {
+    // Detect from the first response that parses
+    #[default]
+    Auto,
+    // focus_score, heart_rate, ... (current bridge)
+    Standard,
+    // focusScore, heartRate, ...
+    CamelCase,
+    // focus, hr, ... (early bridge builds)
+    Compact,
+}
+
+struct FieldNames {
+    attention: &'static str,
+    focus_score: &'static str,
+    brain_state: &'static str,
+    head_orientation: &'static str,
+    heart_rate: &'static str,
+    movement_intensity: &'static str,
+    theta_beta_ratio: &'static str,
+    timestamp: &'static str,
+}
+
+const STANDARD: FieldNames = FieldNames {
+    attention: "attention",
+    focus_score: "focus_score",
+    brain_state: "brain_state",
+    head_orientation: "head_orientation",
+    heart_rate: "heart_rate",
+    movement_intensity: "movement_intensity",
+    theta_beta_ratio: "theta_beta_ratio",
+    timestamp: "timestamp",
+};
+
+const CAMEL_CASE: FieldNames = FieldNames {
+    attention: "attention",
+    focus_score: "focusScore",
+    brain_state: "brainState",
+    head_orientation: "headOrientation",
+    heart_rate: "heartRate",
+    movement_intensity: "movementIntensity",
+    theta_beta_ratio: "thetaBetaRatio",
+    timestamp: "timestamp",
+};
+
+const COMPACT: FieldNames = FieldNames {
+    attention: "attention",
+    focus_score: "focus",
+    brain_state: "brain",
+    head_orientation: "orientation",
+    heart_rate: "hr",
+    movement_intensity: "movement",
+    theta_beta_ratio: "theta_beta",
+    timestamp: "ts",
+};
+
+impl MuseSchema {
+    // Concrete schemas tried, in order, when auto-detecting
+    pub const KNOWN: [MuseSchema; 3] = [MuseSchema::Standard, MuseSchema::CamelCase, MuseSchema::Compact];
+
+    fn field_names(self) -> Option<&'static FieldNames> {
+        match self {
+            MuseSchema::Auto => None,
+            MuseSchema::Standard => Some(&STANDARD),
+            MuseSchema::CamelCase => Some(&CAMEL_CASE),
+            MuseSchema::Compact => Some(&COMPACT),
+        }
+    }
+
+    // Parse with this schema's field names; None if any field is missing or mistyped
+    pub fn parse(self, value: &Value) -> Option<MuseMetrics> {
+        let names = self.field_names()?;
+        let text = |key: &str| value.get(key)?.as_str().map(str::to_string);
+        let number = |key: &str| value.get(key)?.as_f64();
+
+        Some(MuseMetrics {
+            attention: text(names.attention)?,
+            focus_score: number(names.focus_score)?,
+            brain_state: text(names.brain_state)?,
+            head_orientation: text(names.head_orientation)?,
+            heart_rate: number(names.heart_rate)?,
+            movement_intensity: number(names.movement_intensity)?,
+            theta_beta_ratio: number(names.theta_beta_ratio)?,
+            captured_at: value.get(names.timestamp).and_then(parse_timestamp),
+            smoothed_focus_score: None,
+        })
+    }
+
+    // First known schema that parses the value
+    pub fn detect(value: &Value) -> Option<(MuseSchema, MuseMetrics)> {
+        MuseSchema::KNOWN
+            .into_iter()
+            .find_map(|schema| schema.parse(value).map(|metrics| (schema, metrics)))
+    }
+}
+
+// Bridge timestamps may be Unix seconds or RFC 3339 strings
+fn parse_timestamp(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => chrono::DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.timestamp_millis() as f64 / 1000.0),
+        _ => None,
+    }
+}
@@ -0,0 +1,251 @@
+// SQLite persistence for focus history
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+
+// Focus totals for a single local calendar day
+#[derive(Debug, Clone, Serialize)]
+pub struct DayFocus {
+    pub date: String,
+    pub focus_secs: f64,
+    pub monitored_secs: f64,
+    pub avg_score: f64,
+}
+
+// Focus by local hour of day, pooled across days
+#[derive(Debug, Clone, Serialize)]
+pub struct HourFocus {
+    pub hour: u8,
+    pub avg_score: f64,
+    pub samples: u32,
+    // Distinct days with data in this hour
+    pub days: u32,
+}
+
+// A stored sample as read back for timeline building
+#[derive(Debug, Clone)]
+pub struct StoredSample {
+    pub timestamp: String,
+    pub focus_state: String,
+    pub focus_score: f64,
+}
+
+// The monitoring session a sample belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct SessionInfo<'a> {
+    pub id: &'a str,
+    pub tag: Option<&'a str>,
+}
+
+// Focus totals for every sample carrying the same session tag
+#[derive(Debug, Clone, Serialize)]
+pub struct TagStats {
+    pub tag: String,
+    pub avg_score: f64,
+    pub total_mins: f64,
+    pub session_count: u32,
+}
+
+pub fn open_db(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(path)?;
+
+    // WAL keeps per-sample inserts cheap enough for the monitor loop
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS focus_samples (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            focus_state TEXT NOT NULL,
+            focus_score REAL NOT NULL,
+            heart_rate REAL NOT NULL,
+            duration_secs REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_focus_samples_timestamp ON focus_samples(timestamp);
+        CREATE TABLE IF NOT EXISTS state_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            focus_state TEXT NOT NULL,
+            focus_score REAL NOT NULL,
+            brain_state TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_state_changes_timestamp ON state_changes(timestamp);",
+    )?;
+
+    // Session columns were added later; older databases leave them NULL
+    for column in ["session_id", "tag"] {
+        if !has_column(&conn, "focus_samples", column)? {
+            conn.execute_batch(&format!("ALTER TABLE focus_samples ADD COLUMN {} TEXT", column))?;
+        }
+    }
+
+    Ok(conn)
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Record one polled sample; `duration_secs` is the time it stands for
+pub fn log_sample(
+    conn: &Connection,
+    timestamp: &str,
+    focus_state: &str,
+    focus_score: f64,
+    heart_rate: f64,
+    duration_secs: f64,
+    session: SessionInfo,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO focus_samples (timestamp, focus_state, focus_score, heart_rate, duration_secs, session_id, tag)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![timestamp, focus_state, focus_score, heart_rate, duration_secs, session.id, session.tag],
+    )?;
+    Ok(())
+}
+
+// Record a committed focus state change in the session log
+pub fn log_state_change(
+    conn: &Connection,
+    state: &str,
+    timestamp: &str,
+    focus_score: f64,
+    brain_state: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO state_changes (timestamp, focus_state, focus_score, brain_state)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![timestamp, state, focus_score, brain_state],
+    )?;
+    Ok(())
+}
+
+// Per-day totals for local dates between `start_days_ago` and `end_days_ago` (inclusive)
+pub fn daily_focus(
+    conn: &Connection,
+    start_days_ago: u32,
+    end_days_ago: u32,
+) -> rusqlite::Result<Vec<DayFocus>> {
+    let mut stmt = conn.prepare(
+        "SELECT date(timestamp, 'localtime') AS day,
+                SUM(CASE WHEN focus_state = 'focused' THEN duration_secs ELSE 0 END),
+                SUM(duration_secs),
+                AVG(focus_score)
+         FROM focus_samples
+         WHERE day BETWEEN date('now', 'localtime', ?1) AND date('now', 'localtime', ?2)
+         GROUP BY day
+         ORDER BY day",
+    )?;
+
+    let rows = stmt.query_map(
+        params![format!("-{} days", start_days_ago), format!("-{} days", end_days_ago)],
+        |row| {
+            Ok(DayFocus {
+                date: row.get(0)?,
+                focus_secs: row.get(1)?,
+                monitored_secs: row.get(2)?,
+                avg_score: row.get(3)?,
+            })
+        },
+    )?;
+
+    rows.collect()
+}
+
+// Number of times the recorded state switched to unfocused in the same date range
+pub fn count_distractions(
+    conn: &Connection,
+    start_days_ago: u32,
+    end_days_ago: u32,
+) -> rusqlite::Result<u32> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM (
+             SELECT focus_state,
+                    LAG(focus_state) OVER (ORDER BY timestamp) AS previous_state,
+                    date(timestamp, 'localtime') AS day
+             FROM focus_samples
+         )
+         WHERE focus_state = 'unfocused'
+           AND (previous_state IS NULL OR previous_state != 'unfocused')
+           AND day BETWEEN date('now', 'localtime', ?1) AND date('now', 'localtime', ?2)",
+        params![format!("-{} days", start_days_ago), format!("-{} days", end_days_ago)],
+        |row| row.get(0),
+    )
+}
+
+// Average focus for each local hour of day over the last `days_back` days; hours without data are omitted
+pub fn hourly_focus(conn: &Connection, days_back: u32) -> rusqlite::Result<Vec<HourFocus>> {
+    let mut stmt = conn.prepare(
+        "SELECT CAST(strftime('%H', timestamp, 'localtime') AS INTEGER) AS hour,
+                AVG(focus_score),
+                COUNT(*),
+                COUNT(DISTINCT date(timestamp, 'localtime'))
+         FROM focus_samples
+         WHERE date(timestamp, 'localtime') >= date('now', 'localtime', ?1)
+         GROUP BY hour
+         ORDER BY hour",
+    )?;
+
+    let rows = stmt.query_map(params![format!("-{} days", days_back)], |row| {
+        Ok(HourFocus {
+            hour: row.get(0)?,
+            avg_score: row.get(1)?,
+            samples: row.get(2)?,
+            days: row.get(3)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+// Samples with `from <= timestamp <= to`, oldest first; bounds use the stored RFC 3339 format
+pub fn samples_between(conn: &Connection, from: &str, to: &str) -> rusqlite::Result<Vec<StoredSample>> {
+    let mut stmt = conn.prepare(
+        "SELECT timestamp, focus_state, focus_score
+         FROM focus_samples
+         WHERE timestamp BETWEEN ?1 AND ?2
+         ORDER BY timestamp",
+    )?;
+
+    let rows = stmt.query_map(params![from, to], |row| {
+        Ok(StoredSample {
+            timestamp: row.get(0)?,
+            focus_state: row.get(1)?,
+            focus_score: row.get(2)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+// Per-tag totals over all history; samples without a tag are grouped under `untagged_label`
+pub fn stats_by_tag(conn: &Connection, untagged_label: &str) -> rusqlite::Result<Vec<TagStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(tag, ?1) AS tag_name,
+                AVG(focus_score),
+                SUM(duration_secs) / 60.0,
+                COUNT(DISTINCT COALESCE(session_id, date(timestamp, 'localtime')))
+         FROM focus_samples
+         GROUP BY tag_name
+         ORDER BY SUM(duration_secs) DESC",
+    )?;
+
+    let rows = stmt.query_map(params![untagged_label], |row| {
+        Ok(TagStats {
+            tag: row.get(0)?,
+            avg_score: row.get(1)?,
+            total_mins: row.get(2)?,
+            session_count: row.get(3)?,
+        })
+    })?;
+
+    rows.collect()
+}
@@ -0,0 +1,89 @@
+// Optional RFC 5424 syslog output over UDP, enabled with DUCK_SYSLOG=host:port
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+pub const SYSLOG_ENV: &str = "DUCK_SYSLOG";
+
+// local0, the conventional facility for site-specific applications
+const FACILITY_LOCAL0: u8 = 16;
+const APP_NAME: &str = "duck-controller";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Severity {
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+}
+
+pub struct SyslogSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+    hostname: String,
+    // Send failures are reported once, then dropped silently
+    warned: AtomicBool,
+}
+
+impl SyslogSink {
+    // Build a sink from DUCK_SYSLOG; None when unset or the address can't be used
+    pub fn from_env() -> Option<SyslogSink> {
+        let spec = std::env::var(SYSLOG_ENV).ok().filter(|s| !s.trim().is_empty())?;
+        match Self::connect(spec.trim()) {
+            Ok(sink) => {
+                info!("📡 Sending focus events to syslog at {}", sink.target);
+                Some(sink)
+            }
+            Err(e) => {
+                warn!("⚠️ Ignoring {}={}: {}", SYSLOG_ENV, spec, e);
+                None
+            }
+        }
+    }
+
+    fn connect(spec: &str) -> std::io::Result<SyslogSink> {
+        let target = spec.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "address did not resolve")
+        })?;
+        let bind_addr = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(bind_addr)?;
+        // Never stall the monitor loop on a busy or unreachable server
+        socket.set_nonblocking(true)?;
+
+        let hostname = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .ok()
+            .filter(|h| !h.is_empty() && h.is_ascii() && !h.contains(' '))
+            .unwrap_or_else(|| "-".to_string());
+
+        Ok(SyslogSink {
+            socket,
+            target,
+            hostname,
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    // Send one event; `msg_id` names the event kind (e.g. FOCUS, CONNECTION)
+    pub fn send(&self, severity: Severity, msg_id: &str, message: &str) {
+        let line = format_message(&self.hostname, severity, msg_id, message);
+        if let Err(e) = self.socket.send_to(line.as_bytes(), self.target) {
+            if !self.warned.swap(true, Ordering::Relaxed) {
+                warn!("⚠️ Syslog send to {} failed, dropping events: {}", self.target, e);
+            }
+        }
+    }
+}
+
+// <PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+fn format_message(hostname: &str, severity: Severity, msg_id: &str, message: &str) -> String {
+    format!(
+        "<{}>1 {} {} {} {} {} - {}",
+        FACILITY_LOCAL0 * 8 + severity as u8,
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        hostname,
+        APP_NAME,
+        std::process::id(),
+        msg_id,
+        message
+    )
+}
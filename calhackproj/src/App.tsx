@@ -17,6 +17,7 @@ interface DuckMessage {
   message: string;
   timestamp: string;
   type: string;
+  priority?: number;
 }
 
 interface MuseMetrics {
@@ -36,6 +37,7 @@ function App() {
   const [isLoading, setIsLoading] = useState(true);
   const [showTypingTest, setShowTypingTest] = useState(false);
   const [museConnected, setMuseConnected] = useState(false);
+  const [serverPort, setServerPort] = useState(3030);
 
   // Load service status
   async function loadStatus() {
@@ -43,6 +45,7 @@ function App() {
       const result = await invoke<ServiceStatus>("get_service_status");
       setStatus(result);
       setMuseConnected(result.muse_connected);
+      setServerPort(await invoke<number>("get_server_port"));
       setIsLoading(false);
     } catch (error) {
       console.error("Failed to load status:", error);
@@ -225,7 +228,7 @@ function App() {
               {getStatusText(status?.http_server ?? false)}
             </div>
             <div style={{ fontSize: '12px', opacity: 0.7 }}>
-              Port 3030
+              Port {serverPort}
             </div>
           </div>
 
@@ -241,7 +244,7 @@ function App() {
               {getStatusText(status?.websocket_server ?? false)}
             </div>
             <div style={{ fontSize: '12px', opacity: 0.7 }}>
-              ws://localhost:3030/ws
+              ws://localhost:{serverPort}/ws
             </div>
           </div>
 